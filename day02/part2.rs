@@ -1,54 +1,51 @@
-use std::fs;
+use intcode::{parse, IntcodeComputer, IntcodeError, Memory};
+use std::collections::VecDeque;
 
-const END: i32 = 99;
-const SUM: i32 = 1;
-const MUL: i32 = 2;
-const OUTPUT: i32 = 19690720;
+const OUTPUT: i64 = 19690720;
+
+fn run(program: &Memory, noun: i64, verb: i64) -> Result<i64, IntcodeError> {
+    let mut computer = IntcodeComputer::new(program.clone());
 
-fn run(program: &mut Vec<i32>, noun: i32, verb: i32) -> i32 {
     // patch program with noun and verb
-    program[1] = noun;
-    program[2] = verb;
+    computer.program.write(1, noun);
+    computer.program.write(2, verb);
 
     // run program
-    let mut pointer: usize = 0;
-    while program[pointer] != END {
-        let op1_idx = program[pointer + 1] as usize;
-        let op2_idx = program[pointer + 2] as usize;
-        let res_idx = program[pointer + 3] as usize;
-
-        if program[pointer] == SUM {
-            program[res_idx] = program[op1_idx] + program[op2_idx];
-        } else if program[pointer] == MUL {
-            program[res_idx] = program[op1_idx] * program[op2_idx];
-        }
-        pointer += 4;
-    }
-    program[0]
+    computer.run(&mut VecDeque::new())?;
+    Ok(computer.program.read(0))
 }
 
-fn solution(filename: &str) -> i32 {
-    // read file
-    let data = fs::read_to_string(filename)
-        .expect(&format!("File not found: {filename}"));
-
-    // convert file content to vector of integers
-    let program: Vec<i32> = data.split(",")
-        .map(|x| x.trim().parse::<i32>().unwrap())
-        .collect();
-
-    // look all possible values of noun and verb (0 to length of program)
-    let len = program.len();
-    for noun in 0..len {
-        for verb in 0..len {
-            if run(&mut program.to_vec(), noun as i32, verb as i32) == OUTPUT {
-                return 100 * (noun as i32) + (verb as i32);
+fn solution(filename: &str) -> Result<i64, IntcodeError> {
+    let program: Memory = parse(filename)?;
+
+    // look all possible values of noun and verb (0 to 99)
+    for noun in 0..100 {
+        for verb in 0..100 {
+            if run(&program, noun, verb)? == OUTPUT {
+                return Ok(100 * noun + verb);
             }
         }
     }
-    panic!("No soulion found for noun and verb in range 0 to {}", len);
+    Err(IntcodeError::NoSolution {
+        message: format!("no noun and verb from 0 to 99 give {}", OUTPUT),
+    })
 }
 
-fn main() {
-    println!("{}", solution("./input.txt"));    // 5485
+fn main() -> Result<(), IntcodeError> {
+    println!("{}", solution("./input.txt")?);    // 5485
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intcode::parse_program;
+
+    #[test]
+    fn runs_the_examples() {
+        let program: Memory = parse_program("1,9,10,3,2,3,11,0,99,30,40,50").unwrap();
+        assert_eq!(run(&program, 9, 10), Ok(3500));
+        let program: Memory = parse_program("1,0,0,0,99").unwrap();
+        assert_eq!(run(&program, 0, 0), Ok(2));
+    }
 }
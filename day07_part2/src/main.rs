@@ -1,256 +1,278 @@
-use std::collections::VecDeque;
-use std::fs;
-
-const SUM: i32 = 1;
-const MUL: i32 = 2;
-const CPY: i32 = 3;
-const OUT: i32 = 4;
-const JIT: i32 = 5;
-const JIF: i32 = 6;
-const LTH: i32 = 7;
-const EQL: i32 = 8;
-const END: i32 = 99;
+mod topology;
 
-const POSITION_MODE: i32 = 0;
-const IMMEDIATE_MODE: i32 = 1;
+use intcode::{parse, IntcodeComputer, IntcodeError, Memory};
+use std::collections::VecDeque;
+use std::thread;
 
-const N_AMPLIFIERS: usize = 5;
+use topology::Topology;
 
 // Amplifier 'class'
+#[derive(Clone)]
 struct Amplifier {
-    _name: char,
-    program: Vec<i32>,
-    pointer: usize,
-    halted: bool,
+    computer: IntcodeComputer,
 }
 
 impl Amplifier {
-    fn reset(&mut self, original_program: &Vec<i32>) {
-        self.program.copy_from_slice(&original_program);
-        self.pointer = 0;
-        self.halted = false;
+    fn new(program: &Memory) -> Self {
+        Amplifier {
+            computer: IntcodeComputer::new(program.clone()),
+        }
     }
 
-    fn run(&mut self, buffers: &mut Vec<VecDeque<i32>>, amp_pointer: usize) {
-        let input: usize = amp_pointer;
-        let output: usize = (amp_pointer + 1) % N_AMPLIFIERS;
-
-        // run program
-        while self.program[self.pointer] != END {
-            let operation: i32;
-            let first_parameter_mode: i32;
-            let second_parameter_mode: i32;
-            let third_parameter_mode: i32;
-
-            (
-                operation,
-                first_parameter_mode,
-                second_parameter_mode,
-                third_parameter_mode,
-            ) = parse_instruction(self.program[self.pointer]);
-
-            // sanity check
-            if third_parameter_mode == IMMEDIATE_MODE {
-                println!("blah!");
-            }
+    fn halted(&self) -> bool {
+        self.computer.halted
+    }
 
-            let operand1: i32;
-            let operand2: i32;
-            let result_idx: usize;
+    // run until the program halts or needs input that is not there yet,
+    // returning what it printed
+    fn run(&mut self, input: &mut VecDeque<i64>) -> Result<Vec<i64>, IntcodeError> {
+        self.computer.run_until_input(input)
+    }
+}
 
-            // operations with 1 parameter: CPY and OUT
-            if [CPY, OUT].contains(&operation) {
-                let dest_idx = self.program[self.pointer + 1];
-                let to_print: i32;
+// Run the amplifiers with the given phases until they have all halted, and
+// return the last signal out of the output amplifier. None if it never
+// gives one, or the amplifiers end up all waiting on each other.
+fn simulate(
+    program: &Memory,
+    topology: &Topology,
+    phases: &[i64],
+) -> Result<Option<i64>, IntcodeError> {
+    let mut amplifiers: Vec<Amplifier> = vec![Amplifier::new(program); topology.names.len()];
+    let mut buffers: Vec<VecDeque<i64>> = phases
+        .iter()
+        .map(|phase| VecDeque::from([*phase]))
+        .collect();
+    buffers[topology.input].push_back(0);
+    let mut signal: Option<i64> = None;
 
-                if operation == CPY {
-                    // check if there's something in the input
-                    if buffers[input].len() == 0 {
-                        return;
-                    }
-                    self.program[dest_idx as usize] = buffers[input].pop_front().unwrap();
-                } else if operation == OUT {
-                    if first_parameter_mode == POSITION_MODE {
-                        to_print = self.program[dest_idx as usize];
-                    } else {
-                        to_print = dest_idx;
-                    }
-                    buffers[output].push_back(to_print);
-                }
-                self.pointer += 2;
+    while !amplifiers.iter().all(|amp| amp.halted()) {
+        let mut progress: bool = false;
+        for &amp in &topology.order {
+            if amplifiers[amp].halted() {
                 continue;
             }
-
-            // operations with 2 paramters: JIT and JIF
-            if first_parameter_mode == POSITION_MODE {
-                let op1_idx = self.program[self.pointer + 1] as usize;
-                operand1 = self.program[op1_idx];
-            } else {
-                operand1 = self.program[self.pointer + 1];
-            }
-            if second_parameter_mode == POSITION_MODE {
-                let op2_idx = self.program[self.pointer + 2] as usize;
-                operand2 = self.program[op2_idx];
-            } else {
-                operand2 = self.program[self.pointer + 2];
-            }
-
-            if operation == JIT {
-                if operand1 != 0 {
-                    self.pointer = operand2 as usize;
-                    continue;
-                }
-                self.pointer += 3;
-                continue;
-            } else if operation == JIF {
-                if operand1 == 0 {
-                    self.pointer = operand2 as usize;
-                    continue;
-                }
-                self.pointer += 3;
-                continue;
+            progress |= !buffers[amp].is_empty();
+            let output: Vec<i64> = amplifiers[amp].run(&mut buffers[amp])?;
+            progress |= !output.is_empty() || amplifiers[amp].halted();
+            if amp == topology.output && !output.is_empty() {
+                signal = output.last().copied();
             }
-
-            result_idx = self.program[self.pointer + 3] as usize;
-
-            // operations with 3 parameters: SUM, MUL, LTH, and EQL
-            if operation == SUM {
-                self.program[result_idx] = operand1 + operand2;
-            } else if operation == MUL {
-                self.program[result_idx] = operand1 * operand2;
-            } else if operation == LTH {
-                if operand1 < operand2 {
-                    self.program[result_idx] = 1;
-                } else {
-                    self.program[result_idx] = 0;
-                }
-            } else if operation == EQL {
-                if operand1 == operand2 {
-                    self.program[result_idx] = 1;
-                } else {
-                    self.program[result_idx] = 0;
-                }
+            for &next in &topology.edges[amp] {
+                buffers[next].extend(&output);
             }
-            self.pointer += 4;
         }
-        self.halted = true;
+        if !progress {
+            return Ok(None);
+        }
     }
+    Ok(signal)
 }
 
-fn parse(filename: &str) -> Vec<i32> {
-    // read file
-    let data = fs::read_to_string(filename).expect(&format!("File not found: {filename}"));
-
-    // convert content into a vector of integers
-    let program: Vec<i32> = data
-        .split(",")
-        .map(|x| x.trim().parse::<i32>().unwrap())
-        .collect();
-
-    program
-}
-
-fn parse_instruction(instruction: i32) -> (i32, i32, i32, i32) {
-    let operation: i32 = instruction % 100;
-    let parameters: i32 = instruction / 100;
-
-    let first_parameter_mode: i32 = parameters % 10;
-    let parameters: i32 = parameters / 10;
-    let second_parameter_mode: i32 = parameters % 10;
-    let parameters: i32 = parameters / 10;
-    let third_parameter_mode: i32 = parameters % 10;
-
-    (
-        operation,
-        first_parameter_mode,
-        second_parameter_mode,
-        third_parameter_mode,
-    )
+// Depth-first search over phase assignments, in the order the amplifiers
+// run. Phases already taken are skipped, and without feedback each
+// amplifier runs as soon as its phase is chosen, so the runs for a prefix
+// of the assignment are shared by everything that extends it. With
+// feedback nothing is shared: every complete assignment is simulated from
+// scratch.
+struct Search<'a> {
+    program: &'a Memory,
+    topology: &'a Topology,
+    sources: Vec<Vec<usize>>,
+    phases: Vec<i64>,
+    outputs: Vec<Vec<i64>>,
+    best: Option<(i64, Vec<i64>)>,
 }
 
-fn get_permutations(numbers: &mut Vec<i32>) -> Vec<Vec<i32>> {
-    fn dfs(index: usize, numbers: &mut Vec<i32>, result: &mut Vec<Vec<i32>>) {
-        if index == numbers.len() {
-            result.push(numbers.clone());
+impl<'a> Search<'a> {
+    fn new(program: &'a Memory, topology: &'a Topology) -> Self {
+        let n: usize = topology.names.len();
+        Search {
+            program,
+            topology,
+            sources: (0..n).map(|amp| topology.sources(amp)).collect(),
+            phases: vec![0; n],
+            outputs: vec![vec![]; n],
+            best: None,
         }
-        for i in index..numbers.len() {
-            let temp: i32 = numbers[index];
-            numbers[index] = numbers[i];
-            numbers[i] = temp;
-
-            dfs(index + 1, numbers, result);
+    }
 
-            let temp: i32 = numbers[index];
-            numbers[index] = numbers[i];
-            numbers[i] = temp;
+    fn assign(&mut self, depth: usize) -> Result<(), IntcodeError> {
+        if depth == self.topology.order.len() {
+            let signal: Option<i64> = if self.topology.acyclic {
+                self.outputs[self.topology.output].last().copied()
+            } else {
+                simulate(self.program, self.topology, &self.phases)?
+            };
+            if let Some(signal) = signal {
+                if self.best.as_ref().is_none_or(|(best, _)| signal > *best) {
+                    self.best = Some((signal, self.phases.clone()));
+                }
+            }
+            return Ok(());
         }
+        let amp: usize = self.topology.order[depth];
+        for &phase in &self.topology.phases[amp] {
+            self.choose(depth, phase)?;
+        }
+        Ok(())
     }
 
-    let mut result = Vec::new();
-    dfs(0, numbers, &mut result);
-    result
+    fn choose(&mut self, depth: usize, phase: i64) -> Result<(), IntcodeError> {
+        let amp: usize = self.topology.order[depth];
+        let taken: &[usize] = &self.topology.order[..depth];
+        if self.topology.distinct && taken.iter().any(|other| self.phases[*other] == phase) {
+            return Ok(());
+        }
+        self.phases[amp] = phase;
+        if self.topology.acyclic {
+            let mut input: VecDeque<i64> = VecDeque::from([phase]);
+            if amp == self.topology.input {
+                input.push_back(0);
+            }
+            for &source in &self.sources[amp] {
+                input.extend(&self.outputs[source]);
+            }
+            self.outputs[amp] = Amplifier::new(self.program).run(&mut input)?;
+        }
+        self.assign(depth + 1)
+    }
 }
 
-fn all_amps_halted(amplifiers: &Vec<Amplifier>) -> bool {
-    for amp in amplifiers {
-        if !amp.halted {
-            return false;
+// Best signal and the phases giving it. The first amplifier's phase
+// settings are shared out between threads.
+fn search(program: &Memory, topology: &Topology) -> Result<Option<(i64, Vec<i64>)>, IntcodeError> {
+    let first: &[i64] = &topology.phases[topology.order[0]];
+    let threads: usize = thread::available_parallelism()
+        .map_or(1, |threads| threads.get())
+        .min(first.len());
+
+    thread::scope(|scope| {
+        let searches: Vec<_> = (0..threads)
+            .map(|start| {
+                scope.spawn(move || {
+                    let mut search = Search::new(program, topology);
+                    for &phase in first.iter().skip(start).step_by(threads) {
+                        search.choose(0, phase)?;
+                    }
+                    Ok(search.best)
+                })
+            })
+            .collect();
+        let mut best: Option<(i64, Vec<i64>)> = None;
+        for search in searches {
+            if let Some((signal, phases)) = search.join().unwrap()? {
+                if best.as_ref().is_none_or(|(best, _)| signal > *best) {
+                    best = Some((signal, phases));
+                }
+            }
         }
-    }
-    true
+        Ok(best)
+    })
 }
 
-fn solution(filename: &str) -> i32 {
+fn solution(filename: &str, topology: &Topology) -> Result<i64, IntcodeError> {
     // parse file
-    let original_program: Vec<i32> = parse(filename);
-    let mut amplifiers: Vec<Amplifier> = Vec::new();
-    let amp_names: Vec<char> = Vec::from(['A', 'B', 'C', 'D', 'E']);
-
-    for name in amp_names {
-        amplifiers.push(Amplifier {
-            _name: name.clone(),
-            program: original_program.clone(),
-            pointer: 0,
-            halted: false,
-        });
+    let program: Memory = parse(filename)?;
+    match search(&program, topology)? {
+        Some((signal, _)) => Ok(signal),
+        None => Err(IntcodeError::NoSolution {
+            message: "no phase settings give a signal".to_string(),
+        }),
     }
+}
 
-    let mut phases: Vec<i32> = [5, 6, 7, 8, 9].to_vec();
-    let permutations: Vec<Vec<i32>> = get_permutations(&mut phases);
-    let mut buffers: Vec<VecDeque<i32>> = Vec::new();
-    let mut max_output: i32 = 0;
-
-    for phase_perms in permutations {
-        // reset amplifiers
-        for amp in &mut amplifiers {
-            amp.reset(&original_program);
-        }
-        // reset buffers
-        buffers.clear();
-        for i in 0..N_AMPLIFIERS {
-            buffers.push(VecDeque::from([phase_perms[i]]));
-        }
-        buffers[0].push_back(0);
+fn main() -> Result<(), IntcodeError> {
+    let ring: Topology = Topology::ring(5, &[5, 6, 7, 8, 9]);
+    println!("{}", solution("./example1.txt", &ring)?); // 139629729
+    println!("{}", solution("./example2.txt", &ring)?); // 18216
 
-        let mut amp_pointer: usize = 0;
-        while !all_amps_halted(&amplifiers) {
-            amplifiers[amp_pointer].run(&mut buffers, amp_pointer);
-            amp_pointer = (amp_pointer + 1) % N_AMPLIFIERS;
-        }
+    // the same loop, read from a config file
+    let ring: Topology = Topology::load("./ring.txt")?;
+    println!("{}", solution("./input.txt", &ring)?); // 44282086
+    Ok(())
+}
 
-        if buffers[0].len() >= 1 {
-            let output = buffers[0].pop_front().unwrap();
-            if output > max_output {
-                max_output = output;
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intcode::parse_program;
+
+    #[test]
+    fn finds_the_best_phases() {
+        let program: Memory = parse("./example0.txt").unwrap();
+        let chain: Topology = Topology::parse("chain A B C D E\nphases 0-4").unwrap();
+        assert_eq!(
+            search(&program, &chain),
+            Ok(Some((43210, vec![4, 3, 2, 1, 0])))
+        );
+
+        let program: Memory = parse("./example1.txt").unwrap();
+        let ring: Topology = Topology::ring(5, &[5, 6, 7, 8, 9]);
+        assert_eq!(
+            search(&program, &ring),
+            Ok(Some((139629729, vec![9, 8, 7, 6, 5])))
+        );
+
+        // part 1 on the same input
+        let chain: Topology = Topology::parse("chain A B C D E\nphases 0-4").unwrap();
+        assert_eq!(solution("./input.txt", &chain), Ok(34852));
     }
 
-    max_output
-}
+    #[test]
+    fn signals_past_32_bits() {
+        // phase + signal + 2^40, worked out with relative mode
+        let program: Memory = parse_program(
+            "109,30,203,0,203,1,21101,1099511627776,0,2,22201,0,2,2,22201,1,2,2,204,2,99",
+        )
+        .unwrap();
+        let chain: Topology = Topology::parse("chain A B\nphases 1,2").unwrap();
+        assert_eq!(
+            search(&program, &chain),
+            Ok(Some((3 + 2 * 1099511627776, vec![1, 2])))
+        );
+    }
 
-fn main() {
-    println!("{}", solution("./example1.txt")); // 139629729
-    println!("{}", solution("./example2.txt")); // 18216
-    println!("{}", solution("./input.txt")); // 44282086
+    #[test]
+    fn parses_topologies() {
+        let ring: Topology = Topology::parse("ring A B C D E\nphases 5-9\n").unwrap();
+        assert_eq!(ring, Topology::ring(5, &[5, 6, 7, 8, 9]));
+
+        // A feeds B and C, which both feed D
+        let text: &str = "A -> B C\nB -> D # comment\nC -> D\nphases 0-3\nphases C 7,8\nrepeat\n";
+        let dag: Topology = Topology::parse(text).unwrap();
+        assert!(dag.acyclic && !dag.distinct);
+        assert_eq!(dag.order, vec![0, 1, 2, 3]);
+        assert_eq!(dag.sources(3), vec![1, 2]);
+        assert_eq!(dag.phases[2], vec![7, 8]);
+        assert_eq!(dag.output, 3);
+
+        assert_eq!(
+            Topology::parse("chain A B\nA -> B\nphases 0-1"),
+            Err("line 2: A -> B is given twice".to_string())
+        );
+        assert_eq!(
+            Topology::parse("ring A B\nphases A 1-x"),
+            Err("line 2: invalid phase \"x\"".to_string())
+        );
+        assert_eq!(
+            Topology::parse("ring A B"),
+            Err("no phases for amplifier A".to_string())
+        );
+
+        // a leading dash is a sign, and ranges are bounded
+        let signed: Topology = Topology::parse("ring A B\nphases -1,2\nphases B -3--1").unwrap();
+        assert_eq!(signed.phases, vec![vec![-1, 2], vec![-3, -2, -1]]);
+        assert_eq!(
+            Topology::parse("ring A B\nphases 0-9223372036854775807"),
+            Err("line 2: phase range \"0-9223372036854775807\" is longer than 100".to_string())
+        );
+        assert!(Topology::parse("ring A B\nphases -9223372036854775808-1").is_err());
+        assert_eq!(
+            Topology::load("./missing.txt"),
+            Err(IntcodeError::FileNotFound {
+                filename: "./missing.txt".to_string()
+            })
+        );
+    }
 }
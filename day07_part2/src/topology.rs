@@ -0,0 +1,230 @@
+use std::fs;
+
+use intcode::IntcodeError;
+
+// Most phase settings a range may give one amplifier.
+const MAX_PHASES: i64 = 100;
+
+// How the amplifiers are wired together. Every output of an amplifier goes
+// to each amplifier it has an edge to, and the first signal (0) goes in
+// after the phase setting of the input amplifier.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Topology {
+    pub names: Vec<String>,
+    // phase settings each amplifier can be given
+    pub phases: Vec<Vec<i64>>,
+    // where each amplifier's output goes, in the order the edges were given
+    pub edges: Vec<Vec<usize>>,
+    pub input: usize,
+    pub output: usize,
+    // no two amplifiers get the same phase setting
+    pub distinct: bool,
+    // order the amplifiers run in: topological when there is no feedback,
+    // otherwise the order they were declared in
+    pub order: Vec<usize>,
+    pub acyclic: bool,
+}
+
+impl Topology {
+    // Amplifiers 0 to n - 1, each feeding the next and the last feeding back
+    // into the first. Part 2.
+    pub fn ring(n: usize, phases: &[i64]) -> Topology {
+        let edges: Vec<Vec<usize>> = (0..n).map(|amp| vec![(amp + 1) % n]).collect();
+        Topology::new(n, phases, edges)
+    }
+
+    fn new(n: usize, phases: &[i64], edges: Vec<Vec<usize>>) -> Topology {
+        let mut topology = Topology {
+            names: (0..n).map(name).collect(),
+            phases: vec![phases.to_vec(); n],
+            edges,
+            input: 0,
+            output: n.saturating_sub(1),
+            distinct: true,
+            order: vec![],
+            acyclic: false,
+        };
+        topology.sort();
+        topology
+    }
+
+    // Config with one statement per line, and # starting a comment:
+    //   chain A B C      A -> B -> C
+    //   ring A B C       the same, plus C -> A
+    //   A -> B C         A feeds both B and C
+    //   phases 5-9       phases of every amplifier not given its own
+    //   phases A 0,2,4   phases of A only
+    //   input A          where the first signal goes (first amplifier)
+    //   output C         whose last output is the result (last amplifier)
+    //   repeat           amplifiers may share a phase setting
+    pub fn parse(text: &str) -> Result<Topology, String> {
+        let mut topology = Topology {
+            names: vec![],
+            phases: vec![],
+            edges: vec![],
+            input: 0,
+            output: 0,
+            distinct: true,
+            order: vec![],
+            acyclic: false,
+        };
+        let mut own_phases: Vec<Option<Vec<i64>>> = vec![];
+        let mut default_phases: Option<Vec<i64>> = None;
+        let (mut input, mut output): (Option<usize>, Option<usize>) = (None, None);
+
+        for (index, line) in text.lines().enumerate() {
+            let error = |message: String| format!("line {}: {}", index + 1, message);
+            let line: &str = line.split('#').next().unwrap_or("").trim();
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let mut amp = |name: &str| -> usize {
+                match topology.names.iter().position(|known| known == name) {
+                    Some(amp) => amp,
+                    None => {
+                        topology.names.push(name.to_string());
+                        topology.edges.push(vec![]);
+                        own_phases.push(None);
+                        topology.names.len() - 1
+                    }
+                }
+            };
+            let mut connected: Vec<(usize, usize)> = vec![];
+
+            match words.as_slice() {
+                [] => continue,
+                ["chain" | "ring", names @ ..] if !names.is_empty() => {
+                    let amps: Vec<usize> = names.iter().map(|name| amp(name)).collect();
+                    connected.extend(amps.windows(2).map(|pair| (pair[0], pair[1])));
+                    if words[0] == "ring" {
+                        connected.push((amps[amps.len() - 1], amps[0]));
+                    }
+                }
+                ["phases", range] => default_phases = Some(parse_phases(range).map_err(error)?),
+                ["phases", name, range] => {
+                    let phases: Vec<i64> = parse_phases(range).map_err(error)?;
+                    let amp: usize = amp(name);
+                    own_phases[amp] = Some(phases);
+                }
+                ["input", name] => input = Some(amp(name)),
+                ["output", name] => output = Some(amp(name)),
+                ["repeat"] => topology.distinct = false,
+                [from, "->", to @ ..] if !to.is_empty() => {
+                    let from: usize = amp(from);
+                    connected.extend(to.iter().map(|name| (from, amp(name))));
+                }
+                _ => return Err(error(format!("unknown statement {:?}", line))),
+            }
+            for (from, to) in connected {
+                if topology.edges[from].contains(&to) {
+                    return Err(error(format!(
+                        "{} -> {} is given twice",
+                        topology.names[from], topology.names[to]
+                    )));
+                }
+                topology.edges[from].push(to);
+            }
+        }
+
+        if topology.names.is_empty() {
+            return Err("no amplifiers".to_string());
+        }
+        for (amp, phases) in own_phases.into_iter().enumerate() {
+            match phases.or(default_phases.clone()) {
+                Some(phases) => topology.phases.push(phases),
+                None => return Err(format!("no phases for amplifier {}", topology.names[amp])),
+            }
+        }
+        topology.input = input.unwrap_or(0);
+        topology.output = output.unwrap_or(topology.names.len() - 1);
+        topology.sort();
+        Ok(topology)
+    }
+
+    pub fn load(filename: &str) -> Result<Topology, IntcodeError> {
+        let data: String =
+            fs::read_to_string(filename).map_err(|error| IntcodeError::reading(filename, error))?;
+        Topology::parse(&data).map_err(|message| IntcodeError::InvalidConfig {
+            filename: filename.to_string(),
+            message,
+        })
+    }
+
+    // Amplifiers feeding `amp`, in the order they run.
+    pub fn sources(&self, amp: usize) -> Vec<usize> {
+        self.order
+            .iter()
+            .copied()
+            .filter(|source| self.edges[*source].contains(&amp))
+            .collect()
+    }
+
+    // Kahn's algorithm, taking amplifiers in the order they were declared
+    // when there is a choice.
+    fn sort(&mut self) {
+        let n: usize = self.names.len();
+        let mut incoming: Vec<usize> = vec![0; n];
+        for targets in &self.edges {
+            for target in targets {
+                incoming[*target] += 1;
+            }
+        }
+        let mut order: Vec<usize> = vec![];
+        while let Some(amp) = (0..n).find(|amp| incoming[*amp] == 0 && !order.contains(amp)) {
+            order.push(amp);
+            for target in &self.edges[amp] {
+                incoming[*target] -= 1;
+            }
+        }
+        self.acyclic = order.len() == n;
+        self.order = if self.acyclic {
+            order
+        } else {
+            (0..n).collect()
+        };
+    }
+}
+
+// A, B, ..., Z, then A1, B1, ...
+fn name(amp: usize) -> String {
+    let letter: char = (b'A' + (amp % 26) as u8) as char;
+    match amp / 26 {
+        0 => letter.to_string(),
+        round => format!("{}{}", letter, round),
+    }
+}
+
+// "5-9", "-2--1" or "0,-2,4". A dash after the first character splits a
+// range, one at the start is a sign.
+fn parse_phases(text: &str) -> Result<Vec<i64>, String> {
+    let number = |text: &str| {
+        text.parse::<i64>()
+            .map_err(|_| format!("invalid phase {:?}", text))
+    };
+    let range: Option<usize> = text
+        .char_indices()
+        .skip(1)
+        .find(|(_, c)| *c == '-')
+        .map(|(index, _)| index);
+    let phases: Vec<i64> = match range {
+        Some(index) => {
+            let (low, high): (i64, i64) = (number(&text[..index])?, number(&text[index + 1..])?);
+            if high
+                .checked_sub(low)
+                .is_none_or(|length| length >= MAX_PHASES)
+            {
+                return Err(format!(
+                    "phase range {:?} is longer than {}",
+                    text, MAX_PHASES
+                ));
+            }
+            (low..=high).collect()
+        }
+        None => text
+            .split(',')
+            .map(number)
+            .collect::<Result<Vec<i64>, String>>()?,
+    };
+    if phases.is_empty() {
+        return Err(format!("empty phase range {:?}", text));
+    }
+    Ok(phases)
+}
@@ -0,0 +1,13 @@
+// Translate input.txt to Rust ahead of time, see intcode::translate.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+fn main() {
+    println!("cargo:rerun-if-changed=input.txt");
+    let data: String = fs::read_to_string("input.txt").expect("input.txt is missing");
+    let values: Vec<i64> = intcode::parse_values(&data).expect("input.txt is not an Intcode program");
+    let out: PathBuf = PathBuf::from(env::var("OUT_DIR").unwrap()).join("program.rs");
+    fs::write(out, intcode::translate(&values, "program")).unwrap();
+}
@@ -1,13 +1,15 @@
-use std::collections::{HashMap, VecDeque};
-use std::{fmt, fs};
+use std::collections::HashSet;
+use std::fmt;
+use intcode::{parse, AsciiComputer, IntcodeError};
 
 const WALL: i64 = 35;
 const SPACE: i64 = 46;
-const NEWLINE: i64 = 10;
-const NO: i64 = 110;
+// longest main routine or movement function, in characters
+const MAX_LENGTH: usize = 20;
+const FUNCTIONS: [&str; 3] = ["A", "B", "C"];
 
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Direction {
     Up,
     Down,
@@ -27,323 +29,52 @@ impl fmt::Display for Direction {
 }
 
 
-enum OperationType {
-    SUM = 1,
-    MUL = 2,
-    CPY = 3,
-    OUT = 4,
-    JIT = 5,
-    JIF = 6,
-    LTH = 7,
-    EQL = 8,
-    ARB = 9,
-    END = 99,
-}
-
-impl OperationType {
-    fn from_i64(number: i64) -> OperationType {
-        match number {
-            1 => OperationType::SUM,
-            2 => OperationType::MUL,
-            3 => OperationType::CPY,
-            4 => OperationType::OUT,
-            5 => OperationType::JIT,
-            6 => OperationType::JIF,
-            7 => OperationType::LTH,
-            8 => OperationType::EQL,
-            9 => OperationType::ARB,
-            99 => OperationType::END,
-            _ => panic!("Unknown operation: {}", number),
-        }
-    }
-}
-
-#[derive(Debug)]
-enum ParameterMode {
-    PositionMode = 0,
-    ImmediateMode = 1,
-    RelativeMode = 2,
-}
-
-impl ParameterMode {
-    fn from_i64(number: i64) -> ParameterMode {
-        match number {
-            0 => ParameterMode::PositionMode,
-            1 => ParameterMode::ImmediateMode,
-            2 => ParameterMode::RelativeMode,
-            _ => panic!("Unknown parameter mode: {}", number),
-        }
-    }
-}
-
-struct Operation {
-    operation: OperationType,
-    first_parameter_mode: ParameterMode,
-    second_parameter_mode: ParameterMode,
-    third_parameter_mode: ParameterMode,
-}
-
-// IntcodeComputer 'class'
-struct IntcodeComputer {
-    _name: char,
-    program: HashMap<i64, i64>,
-    pointer: i64,
-    halted: bool,
-}
-
-impl IntcodeComputer {
-    fn run(&mut self, input: &mut VecDeque<i64>) -> Vec<i64> {
-        let mut output: Vec<i64> = Vec::new();
-        let mut relative_base: i64 = 0;
-
-        loop {
-            let operation: Operation = self.parse_instruction();
-
-            match operation.operation {
-                OperationType::SUM => self.sum(operation, relative_base),
-                OperationType::MUL => self.mul(operation, relative_base),
-                OperationType::CPY => self.cpy(input, operation, relative_base),
-                OperationType::OUT => self.out(operation, &mut output, relative_base),
-                OperationType::JIT => self.jit(operation, relative_base),
-                OperationType::JIF => self.jif(operation, relative_base),
-                OperationType::LTH => self.lth(operation, relative_base),
-                OperationType::EQL => self.eql(operation, relative_base),
-                OperationType::ARB => self.arb(operation, &mut relative_base),
-                OperationType::END => break,
-            }
+impl Direction {
+    fn from_robot(robot: i64) -> Direction {
+        match robot as u8 {
+            b'v' => Direction::Down,
+            b'<' => Direction::Left,
+            b'>' => Direction::Right,
+            _ => Direction::Up,
         }
-        self.halted = true;
-        output
     }
 
-    fn parse_instruction(&self) -> Operation {
-        let instruction = self.program[&self.pointer];
-        let operation: i64 = instruction % 100;
-        let parameters: i64 = instruction / 100;
-
-        let first_parameter_mode: i64 = parameters % 10;
-        let parameters: i64 = parameters / 10;
-        let second_parameter_mode: i64 = parameters % 10;
-        let parameters: i64 = parameters / 10;
-        let third_parameter_mode: i64 = parameters % 10;
-
-        Operation {
-            operation: OperationType::from_i64(operation),
-            first_parameter_mode: ParameterMode::from_i64(first_parameter_mode),
-            second_parameter_mode: ParameterMode::from_i64(second_parameter_mode),
-            third_parameter_mode: ParameterMode::from_i64(third_parameter_mode),
-        }
-    }
-
-    fn sum(&mut self, operation: Operation, relative_base: i64) {
-        let parameter1: i64 =
-            self.get_first_parameter(operation.first_parameter_mode, relative_base);
-        let parameter2: i64 =
-            self.get_second_parameter(operation.second_parameter_mode, relative_base);
-
-        let result_index: i64 = match operation.third_parameter_mode {
-            ParameterMode::PositionMode => *self.program.entry(self.pointer + 3).or_insert(0),
-            ParameterMode::RelativeMode => {
-                relative_base + *self.program.entry(self.pointer + 3).or_insert(0)
-            }
-            _ => panic!(
-                "Incorrect third parameter mode: {:?}",
-                operation.third_parameter_mode
-            ),
-        };
-
-        self.program.insert(result_index, parameter1 + parameter2);
-        self.pointer += 4;
-    }
-
-    fn get_parameter(
-        &mut self,
-        parameter_mode: ParameterMode,
-        relative_base: i64,
-        offset: i64,
-    ) -> i64 {
-        match parameter_mode {
-            ParameterMode::PositionMode => {
-                let index: i64 = *self.program.entry(self.pointer + offset).or_insert(0);
-                return *self.program.entry(index).or_insert(0);
-            }
-            ParameterMode::ImmediateMode => {
-                return *self.program.entry(self.pointer + offset).or_insert(0)
-            }
-            ParameterMode::RelativeMode => {
-                let index: i64 =
-                    relative_base + *self.program.entry(self.pointer + offset).or_insert(0);
-                return *self.program.entry(index).or_insert(0);
-            }
-        }
-    }
-
-    fn get_first_parameter(
-        &mut self,
-        first_parameter_mode: ParameterMode,
-        relative_base: i64,
-    ) -> i64 {
-        self.get_parameter(first_parameter_mode, relative_base, 1)
-    }
-
-    fn get_second_parameter(
-        &mut self,
-        second_parameter_mode: ParameterMode,
-        relative_base: i64,
-    ) -> i64 {
-        self.get_parameter(second_parameter_mode, relative_base, 2)
-    }
-
-    fn mul(&mut self, operation: Operation, relative_base: i64) {
-        let parameter1: i64 =
-            self.get_first_parameter(operation.first_parameter_mode, relative_base);
-        let parameter2: i64 =
-            self.get_second_parameter(operation.second_parameter_mode, relative_base);
-
-        let result_index: i64 = match operation.third_parameter_mode {
-            ParameterMode::PositionMode => *self.program.entry(self.pointer + 3).or_insert(0),
-            ParameterMode::RelativeMode => {
-                relative_base + *self.program.entry(self.pointer + 3).or_insert(0)
-            }
-            _ => panic!(
-                "Incorrect third parameter mode: {:?}",
-                operation.third_parameter_mode
-            ),
-        };
-
-        self.program.insert(result_index, parameter1 * parameter2);
-        self.pointer += 4;
-    }
-
-    fn cpy(&mut self, inputs: &mut VecDeque<i64>, operation: Operation, relative_base: i64) {
-        let input: i64 = inputs.pop_front().unwrap();
-        match operation.first_parameter_mode {
-            ParameterMode::PositionMode => {
-                let index: i64 = *self.program.entry(self.pointer + 1).or_insert(0);
-                self.program.insert(index, input);
-            }
-            ParameterMode::RelativeMode => {
-                let index: i64 = relative_base + *self.program.entry(self.pointer + 1).or_insert(0);
-                self.program.insert(index, input);
-            }
-            _ => panic!(
-                "Incorrect first parameter mode: {:?}",
-                operation.first_parameter_mode
-            ),
-        }
-        self.pointer += 2;
-    }
-
-    fn out(&mut self, operation: Operation, output: &mut Vec<i64>, relative_base: i64) {
-        let operand: i64 = self.get_first_parameter(operation.first_parameter_mode, relative_base);
-        output.push(operand);
-        self.pointer += 2;
-    }
-
-    fn jit(&mut self, operation: Operation, relative_base: i64) {
-        let parameter1: i64 =
-            self.get_first_parameter(operation.first_parameter_mode, relative_base);
-        let parameter2: i64 =
-            self.get_second_parameter(operation.second_parameter_mode, relative_base);
-
-        if parameter1 != 0 {
-            self.pointer = parameter2;
-        } else {
-            self.pointer += 3;
+    fn step(&self) -> (i32, i32) {
+        match self {
+            Direction::Up => (-1, 0),
+            Direction::Down => (1, 0),
+            Direction::Left => (0, -1),
+            Direction::Right => (0, 1),
         }
     }
 
-    fn jif(&mut self, operation: Operation, relative_base: i64) {
-        let parameter1: i64 =
-            self.get_first_parameter(operation.first_parameter_mode, relative_base);
-        let parameter2: i64 =
-            self.get_second_parameter(operation.second_parameter_mode, relative_base);
-
-        if parameter1 == 0 {
-            self.pointer = parameter2;
-        } else {
-            self.pointer += 3;
+    fn clockwise(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
         }
     }
 
-    fn lth(&mut self, operation: Operation, relative_base: i64) {
-        let parameter1: i64 =
-            self.get_first_parameter(operation.first_parameter_mode, relative_base);
-        let parameter2: i64 =
-            self.get_second_parameter(operation.second_parameter_mode, relative_base);
-
-        let result_index: i64 = match operation.third_parameter_mode {
-            ParameterMode::PositionMode => *self.program.entry(self.pointer + 3).or_insert(0),
-            ParameterMode::RelativeMode => {
-                relative_base + *self.program.entry(self.pointer + 3).or_insert(0)
-            }
-            _ => panic!(
-                "Incorrect third parameter mode: {:?}",
-                operation.third_parameter_mode
-            ),
-        };
-
-        if parameter1 < parameter2 {
-            self.program.insert(result_index, 1);
-        } else {
-            self.program.insert(result_index, 0);
-        }
-        self.pointer += 4;
+    fn counterclockwise(&self) -> Direction {
+        self.clockwise().clockwise().clockwise()
     }
 
-    fn eql(&mut self, operation: Operation, relative_base: i64) {
-        let parameter1: i64 =
-            self.get_first_parameter(operation.first_parameter_mode, relative_base);
-        let parameter2: i64 =
-            self.get_second_parameter(operation.second_parameter_mode, relative_base);
-
-        let result_index: i64 = match operation.third_parameter_mode {
-            ParameterMode::PositionMode => *self.program.entry(self.pointer + 3).or_insert(0),
-            ParameterMode::RelativeMode => {
-                relative_base + *self.program.entry(self.pointer + 3).or_insert(0)
-            }
-            _ => panic!(
-                "Incorrect third parameter mode: {:?}",
-                operation.third_parameter_mode
-            ),
-        };
-
-        if parameter1 == parameter2 {
-            self.program.insert(result_index, 1);
+    // turns that take the robot from facing `self` to facing `next`
+    fn turns_to(&self, next: Direction) -> Vec<&'static str> {
+        if next == *self {
+            vec![]
+        } else if next == self.clockwise() {
+            vec!["R"]
+        } else if next == self.counterclockwise() {
+            vec!["L"]
         } else {
-            self.program.insert(result_index, 0);
+            vec!["R", "R"]
         }
-        self.pointer += 4;
-    }
-
-    fn arb(&mut self, operation: Operation, relative_base: &mut i64) {
-        let parameter1: i64 =
-            self.get_first_parameter(operation.first_parameter_mode, *relative_base);
-        *relative_base += parameter1;
-
-        self.pointer += 2;
     }
 }
 
-fn parse(filename: &str) -> HashMap<i64, i64> {
-    // read file
-    let data = fs::read_to_string(filename).expect(&format!("File not found: {filename}"));
-
-    // convert content into a vector of integers
-    let vec_data: Vec<i64> = data
-        .split(",")
-        .map(|x| x.trim().parse::<i64>().unwrap())
-        .collect();
-
-    let mut program: HashMap<i64, i64> = HashMap::new();
-
-    for (index, value) in vec_data.iter().enumerate() {
-        program.insert(index as i64, *value);
-    }
-
-    program
-}
-
 fn get_next_dir(
     board: &Vec<Vec<i64>>,
     current_dir: &Direction,
@@ -399,30 +130,199 @@ fn get_next_position(
     None
 }
 
-fn solution(filename: &str) -> i32 {
-    let mut computer = IntcodeComputer {
-        _name: ' ',
-        program: parse(filename),
-        pointer: 0,
-        halted: false,
-    };
+// Group instructions into moves, each the turns before a stretch and its
+// length. Routines can only be split between moves.
+fn moves(instructions: &[String]) -> Vec<String> {
+    let mut moves: Vec<String> = vec![];
+    let mut current: Vec<&str> = vec![];
+    for instruction in instructions {
+        current.push(instruction);
+        if instruction.parse::<i32>().is_ok() {
+            moves.push(current.join(","));
+            current.clear();
+        }
+    }
+    moves
+}
+
+fn compress_from<'a>(
+    moves: &'a [String],
+    position: usize,
+    functions: &mut Vec<&'a [String]>,
+    main: &mut Vec<usize>,
+) -> bool {
+    if position == moves.len() {
+        return true;
+    }
+    // "A,B,..." has one comma fewer than calls
+    if 2 * (main.len() + 1) - 1 > MAX_LENGTH {
+        return false;
+    }
+    for index in 0..functions.len() {
+        let function: &[String] = functions[index];
+        if moves[position..].starts_with(function) {
+            main.push(index);
+            if compress_from(moves, position + function.len(), functions, main) {
+                return true;
+            }
+            main.pop();
+        }
+    }
+    if functions.len() < FUNCTIONS.len() {
+        for end in position + 1..=moves.len() {
+            let function: &[String] = &moves[position..end];
+            if function.join(",").len() > MAX_LENGTH {
+                break;
+            }
+            functions.push(function);
+            main.push(functions.len() - 1);
+            if compress_from(moves, end, functions, main) {
+                return true;
+            }
+            main.pop();
+            functions.pop();
+        }
+    }
+    false
+}
+
+// Main routine and the three movement functions that spell out `moves`,
+// if they fit in the robot's memory.
+fn compress(moves: &[String]) -> Option<(String, Vec<String>)> {
+    // an empty main routine is nothing the robot can run
+    if moves.is_empty() {
+        return None;
+    }
+    let mut functions: Vec<&[String]> = vec![];
+    let mut main: Vec<usize> = vec![];
+    if !compress_from(moves, 0, &mut functions, &mut main) {
+        return None;
+    }
+    let main: Vec<&str> = main.iter().map(|index| FUNCTIONS[*index]).collect();
+    let mut functions: Vec<String> = functions
+        .iter()
+        .map(|function| function.join(","))
+        .collect();
+    // the robot asks for all three even when fewer are needed
+    while functions.len() < FUNCTIONS.len() {
+        functions.push(functions[0].clone());
+    }
+    Some((main.join(","), functions))
+}
+
+fn is_scaffold(board: &[Vec<i64>], row: i32, col: i32) -> bool {
+    row >= 0
+        && col >= 0
+        && (row as usize) < board.len()
+        && (col as usize) < board[row as usize].len()
+        && board[row as usize][col as usize] != SPACE
+}
+
+// Instructions for a walk given as the direction of every single step.
+fn instructions_for(facing: Direction, walk: &[Direction]) -> Vec<String> {
+    let mut instructions: Vec<String> = vec![];
+    let mut current_dir: Direction = facing;
+    let mut index: usize = 0;
+    while index < walk.len() {
+        let dir: Direction = walk[index];
+        let distance: usize = walk[index..]
+            .iter()
+            .take_while(|next| **next == dir)
+            .count();
+        instructions.extend(current_dir.turns_to(dir).iter().map(|turn| turn.to_string()));
+        instructions.push(distance.to_string());
+        current_dir = dir;
+        index += distance;
+    }
+    instructions
+}
 
-    let mut input: VecDeque<i64> = VecDeque::new();
-    let output = computer.run(&mut input);
+struct Walk<'a, T> {
+    board: &'a Vec<Vec<i64>>,
+    // stretches between neighbouring cells already run over
+    used: HashSet<((usize, usize), (usize, usize))>,
+    visits: Vec<Vec<usize>>,
+    uncovered: usize,
+    steps: Vec<Direction>,
+    found: &'a mut dyn FnMut(&[Direction]) -> Option<T>,
+}
+
+impl<T> Walk<'_, T> {
+    fn extend(&mut self, (row, col): (usize, usize), heading: Direction) -> Option<T> {
+        if self.uncovered == 0 {
+            return (self.found)(&self.steps);
+        }
+        // carrying straight on first keeps the straight-through path first
+        let mut choices: Vec<Direction> =
+            vec![heading, heading.counterclockwise(), heading.clockwise()];
+        if self.steps.is_empty() {
+            choices.push(heading.clockwise().clockwise());
+        }
+        for dir in choices {
+            let (step_row, step_col) = dir.step();
+            let (next_row, next_col) = (row as i32 + step_row, col as i32 + step_col);
+            if !is_scaffold(self.board, next_row, next_col) {
+                continue;
+            }
+            let next: (usize, usize) = (next_row as usize, next_col as usize);
+            let edge = ((row, col).min(next), (row, col).max(next));
+            if !self.used.insert(edge) {
+                continue;
+            }
+            self.visits[next.0][next.1] += 1;
+            if self.visits[next.0][next.1] == 1 {
+                self.uncovered -= 1;
+            }
+            self.steps.push(dir);
 
-    let mut scaffolding: Vec<Vec<i64>> = vec![];
-    let mut scaffolding_row: Vec<i64> = vec![];
+            let result: Option<T> = self.extend(next, dir);
 
-    for ascii in &output {
-        if *ascii == NEWLINE {
-            if scaffolding_row.len() > 0 {
-                scaffolding.push(scaffolding_row);
-                scaffolding_row = vec![];
+            self.steps.pop();
+            if self.visits[next.0][next.1] == 1 {
+                self.uncovered += 1;
+            }
+            self.visits[next.0][next.1] -= 1;
+            self.used.remove(&edge);
+            if result.is_some() {
+                return result;
             }
-        } else {
-            scaffolding_row.push(*ascii);
         }
+        None
     }
+}
+
+// Try every walk from the robot that covers the whole scaffold without
+// running over any stretch twice, turning or not at intersections, until
+// `found` accepts one.
+fn walks<T>(
+    board: &Vec<Vec<i64>>,
+    start: (usize, usize),
+    facing: Direction,
+    found: &mut dyn FnMut(&[Direction]) -> Option<T>,
+) -> Option<T> {
+    let mut visits: Vec<Vec<usize>> = board.iter().map(|row| vec![0; row.len()]).collect();
+    visits[start.0][start.1] = 1;
+    let cells: usize = board.iter().flatten().filter(|cell| **cell != SPACE).count();
+    let mut walk = Walk {
+        board,
+        used: HashSet::new(),
+        visits,
+        uncovered: cells - 1,
+        steps: vec![],
+        found,
+    };
+    walk.extend(start, facing)
+}
+
+fn solution(filename: &str) -> Result<i64, IntcodeError> {
+    let mut computer = AsciiComputer::new(parse(filename)?);
+    let view: String = computer.read_until_prompt()?;
+
+    let scaffolding: Vec<Vec<i64>> = view
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.chars().map(|c| c as i64).collect())
+        .collect();
     let rows: usize = scaffolding.len();
     let cols: usize = scaffolding[0].len();
 
@@ -442,8 +342,11 @@ fn solution(filename: &str) -> i32 {
             }
         }
     }
+    let start: (usize, usize) = (robot_row, robot_col);
+    let facing: Direction = Direction::from_robot(scaffolding[robot_row][robot_col]);
+
     let mut instructions: Vec<String> = vec![];
-    let mut current_dir: Direction = Direction::Up;
+    let mut current_dir: Direction = facing;
 
     while let Some((dir, turn, step)) = get_next_dir(&scaffolding, &current_dir, robot_row, robot_col) {
         current_dir = dir;
@@ -464,42 +367,107 @@ fn solution(filename: &str) -> i32 {
             instructions.push(distance.to_string());
         }
     }
-    // println!("{:?}", instructions);
 
-    let a: Vec<&str> = vec!["L", "12", "L", "12", "L", "6", "L", "6"];
-    let b: Vec<&str> = vec!["R", "8", "R", "4", "L", "12"];
-    let c: Vec<&str> = vec!["L", "12", "L", "6", "R", "12", "R", "8"];
-    
-    let movements: Vec<&str> = vec!["A", "B", "A", "C", "B", "A", "C", "B", "A", "C"];
-
-    let mut input: VecDeque<i64> = VecDeque::new();
-    for movement in movements.join(",").chars() {
-        input.push_back(movement as i64);
+    // the path that goes straight through every intersection, then the
+    // ones that turn at some of them
+    let mut routines: Option<(String, Vec<String>)> = compress(&moves(&instructions));
+    if routines.is_none() {
+        routines = walks(&scaffolding, start, facing, &mut |walk| {
+            compress(&moves(&instructions_for(facing, walk)))
+        });
     }
-    input.push_back(NEWLINE);
-
-    for movement in [a, b, c] {
-        for action in movement.join(",").chars() {
-            input.push_back(action as i64);
+    let (movements, functions) = match routines {
+        Some(routines) => routines,
+        None => {
+            return Err(IntcodeError::NoSolution {
+                message: "the path does not fit in three movement functions".to_string(),
+            })
         }
-        input.push_back(NEWLINE);
-    }
-    input.push_back(NO);    // no continuous video feed
-    input.push_back(NEWLINE);
-
-    let mut computer = IntcodeComputer {
-        _name: ' ',
-        program: parse(filename),
-        pointer: 0,
-        halted: false,
     };
+
+    let mut computer = AsciiComputer::new(parse(filename)?);
     // patch program
-    computer.program.insert(0, 2);
-    let output = computer.run(&mut input);
+    computer.computer_mut().program.write(0, 2);
+    computer.send_line(&movements)?;
+    for function in &functions {
+        computer.send_line(function)?;
+    }
+    computer.send_line("n")?; // no continuous video feed
+    computer.read_until_prompt()?;
+
+    computer.answer().ok_or(IntcodeError::NoSolution {
+        message: "the robot did not report the dust it collected".to_string(),
+    })
+}
 
-    output[output.len() - 1] as i32
+fn main() -> Result<(), IntcodeError> {
+    println!("{:?}", solution("./input.txt")?); // 982279
+    Ok(())
 }
 
-fn main() {
-    println!("{:?}", solution("./input.txt")); // 982279
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // example from the puzzle, with its robot facing up
+    const EXAMPLE: &str = "#######...#####
+#.....#...#...#
+#.....#...#...#
+......#...#...#
+......#...###.#
+......#.....#.#
+^########...#.#
+......#.#...#.#
+......#########
+........#...#..
+....#########..
+....#...#......
+....#...#......
+....#...#......
+....#####......";
+
+    const EXAMPLE_PATH: &str = "R,8,R,8,R,4,R,4,R,8,L,6,L,2,R,4,R,4,R,8,R,8,R,8,L,6,L,2";
+
+    fn example() -> Vec<Vec<i64>> {
+        EXAMPLE
+            .lines()
+            .map(|line| line.chars().map(|c| c as i64).collect())
+            .collect()
+    }
+
+    fn split(path: &str) -> Vec<String> {
+        path.split(',').map(|token| token.to_string()).collect()
+    }
+
+    #[test]
+    fn compresses_the_example_path() {
+        let moves: Vec<String> = moves(&split(EXAMPLE_PATH));
+        let (main, functions) = compress(&moves).unwrap();
+        assert!(main.len() <= MAX_LENGTH);
+        assert!(functions.iter().all(|function| function.len() <= MAX_LENGTH));
+        let expanded: Vec<&str> = main
+            .split(',')
+            .map(|name| {
+                let index: usize = FUNCTIONS.iter().position(|f| *f == name).unwrap();
+                functions[index].as_str()
+            })
+            .collect();
+        assert_eq!(expanded.join(","), EXAMPLE_PATH);
+        assert_eq!(compress(&[]), None);
+    }
+
+    #[test]
+    fn walks_start_with_the_straight_through_path() {
+        let board: Vec<Vec<i64>> = example();
+        let mut paths: Vec<String> = vec![];
+        walks(&board, (6, 0), Direction::Up, &mut |walk| {
+            paths.push(instructions_for(Direction::Up, walk).join(","));
+            None::<()>
+        });
+        assert_eq!(paths[0], EXAMPLE_PATH);
+        // turning at some of the intersections gives other ways round
+        assert_eq!(paths.len(), 16);
+        let distinct: HashSet<&String> = paths.iter().collect();
+        assert_eq!(distinct.len(), paths.len());
+    }
 }
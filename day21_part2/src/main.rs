@@ -0,0 +1,23 @@
+use intcode::{parse, solve, IntcodeError, Mode};
+
+fn solution(filename: &str) -> Result<i64, IntcodeError> {
+    solve(&parse(filename)?, Mode::Run)
+}
+
+fn main() -> Result<(), IntcodeError> {
+    println!("{:?}", solution("./input.txt")?); // 1141457530
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gets_across_the_hull() {
+        assert_eq!(
+            solution(concat!(env!("CARGO_MANIFEST_DIR"), "/input.txt")),
+            Ok(1141457530)
+        );
+    }
+}
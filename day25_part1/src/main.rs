@@ -1,439 +1,320 @@
+use intcode::{parse, AsciiComputer, IntcodeError};
 use std::collections::{HashMap, VecDeque};
-use std::fs;
-// use std::io;
 
-enum OperationType {
-    SUM = 1,
-    MUL = 2,
-    CPY = 3,
-    OUT = 4,
-    JIT = 5,
-    JIF = 6,
-    LTH = 7,
-    EQL = 8,
-    ARB = 9,
-    END = 99,
+// Commands normally finish in well under this many instructions. Items like
+// the infinite loop never give the prompt back.
+const MAX_STEPS: usize = 1_000_000;
+// Every subset of the carried items may need to be weighed.
+const MAX_ITEMS: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Room {
+    name: String,
+    doors: Vec<String>,
+    items: Vec<String>,
 }
 
-impl OperationType {
-    fn from_i64(number: i64) -> OperationType {
-        match number {
-            1 => OperationType::SUM,
-            2 => OperationType::MUL,
-            3 => OperationType::CPY,
-            4 => OperationType::OUT,
-            5 => OperationType::JIT,
-            6 => OperationType::JIF,
-            7 => OperationType::LTH,
-            8 => OperationType::EQL,
-            9 => OperationType::ARB,
-            99 => OperationType::END,
-            _ => panic!("Unknown operation: {}", number),
+// Parse the last room description in `text`. Stepping on the pressure plate
+// prints two: the plate's room, then the one the droid is thrown back to.
+fn parse_room(text: &str) -> Option<Room> {
+    let lines: Vec<&str> = text.lines().collect();
+    let start: usize = lines
+        .iter()
+        .rposition(|line| line.starts_with("== ") && line.ends_with(" =="))?;
+    let mut room = Room {
+        name: lines[start]
+            .trim_matches(|c| c == '=' || c == ' ')
+            .to_string(),
+        doors: vec![],
+        items: vec![],
+    };
+    let mut list: Option<&mut Vec<String>> = None;
+    for line in &lines[start + 1..] {
+        match *line {
+            "Doors here lead:" => list = Some(&mut room.doors),
+            "Items here:" => list = Some(&mut room.items),
+            _ => match (line.strip_prefix("- "), list.as_mut()) {
+                (Some(entry), Some(list)) => list.push(entry.to_string()),
+                _ => list = None,
+            },
         }
     }
+    Some(room)
 }
 
-#[derive(Debug)]
-enum ParameterMode {
-    PositionMode = 0,
-    ImmediateMode = 1,
-    RelativeMode = 2,
-}
-
-impl ParameterMode {
-    fn from_i64(number: i64) -> ParameterMode {
-        match number {
-            0 => ParameterMode::PositionMode,
-            1 => ParameterMode::ImmediateMode,
-            2 => ParameterMode::RelativeMode,
-            _ => panic!("Unknown parameter mode: {}", number),
-        }
+fn opposite(door: &str) -> &str {
+    match door {
+        "north" => "south",
+        "south" => "north",
+        "east" => "west",
+        _ => "east",
     }
 }
 
-struct Operation {
-    operation: OperationType,
-    first_parameter_mode: ParameterMode,
-    second_parameter_mode: ParameterMode,
-    third_parameter_mode: ParameterMode,
+// Airlock password in the text printed once the droid gets through.
+fn password(text: &str) -> Option<i64> {
+    text.split_whitespace()
+        .rev()
+        .find_map(|word| word.parse::<i64>().ok())
 }
 
-// IntcodeComputer 'class'
-struct IntcodeComputer {
-    program: HashMap<i64, i64>,
-    pointer: i64,
-    halted: bool,
-    relative_base: i64,
+// Droid that maps the ship, picks up every item that is safe to carry and
+// then works out which of them get it past the pressure plate.
+struct Explorer {
+    computer: AsciiComputer,
+    here: String,
+    rooms: HashMap<String, Room>,
+    // (room, door) -> room the door leads to
+    doors: HashMap<(String, String), String>,
+    carried: Vec<String>,
+    dangerous: Vec<String>,
+    // room with the pressure plate door, and that door
+    checkpoint: Option<(String, String)>,
+    password: Option<i64>,
 }
 
-impl IntcodeComputer {
-    fn run(&mut self, input: &mut VecDeque<i64>) -> Vec<i64> {
-        let mut output: Vec<i64> = vec![];
-        loop {
-            let operation: Operation = self.parse_instruction();
-
-            match operation.operation {
-                OperationType::SUM => self.sum(operation),
-                OperationType::MUL => self.mul(operation),
-                OperationType::CPY => {
-                    if input.len() == 0 {
-                        return output;
-                    }
-                    self.cpy(input, operation);
-                }
-                OperationType::OUT => self.out(operation, &mut output),
-                OperationType::JIT => self.jit(operation),
-                OperationType::JIF => self.jif(operation),
-                OperationType::LTH => self.lth(operation),
-                OperationType::EQL => self.eql(operation),
-                OperationType::ARB => self.arb(operation),
-                OperationType::END => break,
-            }
+impl Explorer {
+    fn new(computer: AsciiComputer) -> Self {
+        Explorer {
+            computer,
+            here: String::new(),
+            rooms: HashMap::new(),
+            doors: HashMap::new(),
+            carried: vec![],
+            dangerous: vec![],
+            checkpoint: None,
+            password: None,
         }
-        self.halted = true;
-        output
     }
 
-    fn parse_instruction(&self) -> Operation {
-        let instruction = self.program[&self.pointer];
-        let operation: i64 = instruction % 100;
-        let parameters: i64 = instruction / 100;
-
-        let first_parameter_mode: i64 = parameters % 10;
-        let parameters: i64 = parameters / 10;
-        let second_parameter_mode: i64 = parameters % 10;
-        let parameters: i64 = parameters / 10;
-        let third_parameter_mode: i64 = parameters % 10;
-
-        Operation {
-            operation: OperationType::from_i64(operation),
-            first_parameter_mode: ParameterMode::from_i64(first_parameter_mode),
-            second_parameter_mode: ParameterMode::from_i64(second_parameter_mode),
-            third_parameter_mode: ParameterMode::from_i64(third_parameter_mode),
+    fn command(&mut self, line: &str) -> Result<String, IntcodeError> {
+        self.computer.send_line(line)?;
+        let text: String = self.computer.read_until_prompt()?;
+        if self.computer.is_halted() {
+            self.password = password(&text);
         }
+        Ok(text)
     }
 
-    fn sum(&mut self, operation: Operation) {
-        let parameter1: i64 = self.get_first_parameter(operation.first_parameter_mode);
-        let parameter2: i64 = self.get_second_parameter(operation.second_parameter_mode);
-
-        let result_index: i64 = match operation.third_parameter_mode {
-            ParameterMode::PositionMode => *self.program.entry(self.pointer + 3).or_insert(0),
-            ParameterMode::RelativeMode => {
-                self.relative_base + *self.program.entry(self.pointer + 3).or_insert(0)
-            }
-            _ => panic!(
-                "Incorrect third parameter mode: {:?}",
-                operation.third_parameter_mode
-            ),
-        };
-
-        self.program.insert(result_index, parameter1 + parameter2);
-        self.pointer += 4;
+    // Try the item on a copy of the droid: it is dangerous if picking it up
+    // ends the game, never returns, or leaves the droid unable to move.
+    fn is_safe(&self, item: &str, room: &Room) -> Result<bool, IntcodeError> {
+        let mut trial: AsciiComputer = self.computer.fork();
+        trial.send_line(&format!("take {}", item))?;
+        if trial.read_until_prompt_within(MAX_STEPS)?.is_none() || trial.is_halted() {
+            return Ok(false);
+        }
+        let door: &String = room.doors.first().ok_or_else(|| IntcodeError::NoSolution {
+            message: format!("{} has no doors to leave by", room.name),
+        })?;
+        trial.send_line(door)?;
+        let text: Option<String> = trial.read_until_prompt_within(MAX_STEPS)?;
+        Ok(text.is_some_and(|text| parse_room(&text).is_some()))
     }
 
-    fn get_parameter(&mut self, parameter_mode: ParameterMode, offset: i64) -> i64 {
-        match parameter_mode {
-            ParameterMode::PositionMode => {
-                let index: i64 = *self.program.entry(self.pointer + offset).or_insert(0);
-                return *self.program.entry(index).or_insert(0);
-            }
-            ParameterMode::ImmediateMode => {
-                return *self.program.entry(self.pointer + offset).or_insert(0)
-            }
-            ParameterMode::RelativeMode => {
-                let index: i64 =
-                    self.relative_base + *self.program.entry(self.pointer + offset).or_insert(0);
-                return *self.program.entry(index).or_insert(0);
+    // Depth-first walk from `room`, where the droid is, and back again.
+    fn explore(&mut self, room: Room) -> Result<(), IntcodeError> {
+        self.here = room.name.clone();
+        self.rooms.insert(room.name.clone(), room.clone());
+        for item in &room.items {
+            if self.is_safe(item, &room)? {
+                self.command(&format!("take {}", item))?;
+                self.carried.push(item.clone());
+            } else {
+                self.dangerous.push(item.clone());
             }
         }
-    }
-
-    fn get_first_parameter(&mut self, first_parameter_mode: ParameterMode) -> i64 {
-        self.get_parameter(first_parameter_mode, 1)
-    }
-
-    fn get_second_parameter(&mut self, second_parameter_mode: ParameterMode) -> i64 {
-        self.get_parameter(second_parameter_mode, 2)
-    }
 
-    fn mul(&mut self, operation: Operation) {
-        let parameter1: i64 = self.get_first_parameter(operation.first_parameter_mode);
-        let parameter2: i64 = self.get_second_parameter(operation.second_parameter_mode);
-
-        let result_index: i64 = match operation.third_parameter_mode {
-            ParameterMode::PositionMode => *self.program.entry(self.pointer + 3).or_insert(0),
-            ParameterMode::RelativeMode => {
-                self.relative_base + *self.program.entry(self.pointer + 3).or_insert(0)
+        for door in &room.doors {
+            if self.doors.contains_key(&(room.name.clone(), door.clone())) {
+                continue;
             }
-            _ => panic!(
-                "Incorrect third parameter mode: {:?}",
-                operation.third_parameter_mode
-            ),
-        };
-
-        self.program.insert(result_index, parameter1 * parameter2);
-        self.pointer += 4;
-    }
-
-    fn cpy(&mut self, inputs: &mut VecDeque<i64>, operation: Operation) {
-        let input: i64 = inputs.pop_front().unwrap();
-        match operation.first_parameter_mode {
-            ParameterMode::PositionMode => {
-                let index: i64 = *self.program.entry(self.pointer + 1).or_insert(0);
-                self.program.insert(index, input);
+            let text: String = self.command(door)?;
+            if self.computer.is_halted() {
+                return Ok(());
             }
-            ParameterMode::RelativeMode => {
-                let index: i64 =
-                    self.relative_base + *self.program.entry(self.pointer + 1).or_insert(0);
-                self.program.insert(index, input);
+            let next: Room = match parse_room(&text) {
+                Some(next) => next,
+                None => continue,
+            };
+            if next.name == room.name {
+                // thrown back by the pressure plate
+                self.checkpoint = Some((room.name.clone(), door.clone()));
+                self.doors
+                    .insert((room.name.clone(), door.clone()), room.name.clone());
+                continue;
             }
-            _ => panic!(
-                "Incorrect first parameter mode: {:?}",
-                operation.first_parameter_mode
-            ),
+            self.doors
+                .insert((room.name.clone(), door.clone()), next.name.clone());
+            self.doors.insert(
+                (next.name.clone(), opposite(door).to_string()),
+                room.name.clone(),
+            );
+            if !self.rooms.contains_key(&next.name) {
+                self.explore(next)?;
+                if self.computer.is_halted() {
+                    return Ok(());
+                }
+            }
+            self.command(opposite(door))?;
+            self.here = room.name.clone();
         }
-        self.pointer += 2;
+        Ok(())
     }
 
-    fn out(&mut self, operation: Operation, output: &mut Vec<i64>) {
-        let operand: i64 = self.get_first_parameter(operation.first_parameter_mode);
-        output.push(operand);
-
-        self.pointer += 2;
-    }
-
-    fn jit(&mut self, operation: Operation) {
-        let parameter1: i64 = self.get_first_parameter(operation.first_parameter_mode);
-        let parameter2: i64 = self.get_second_parameter(operation.second_parameter_mode);
-
-        if parameter1 != 0 {
-            self.pointer = parameter2;
-        } else {
-            self.pointer += 3;
+    // Doors to go through from where the droid is to `target`.
+    fn path_to(&self, target: &str) -> Option<Vec<String>> {
+        let mut previous: HashMap<String, (String, String)> = HashMap::new();
+        let mut queue: VecDeque<String> = VecDeque::from([self.here.clone()]);
+        while let Some(room) = queue.pop_front() {
+            if room == target {
+                let mut path: Vec<String> = vec![];
+                let mut room: String = room;
+                while let Some((from, door)) = previous.get(&room) {
+                    path.push(door.clone());
+                    room = from.clone();
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for door in &self.rooms[&room].doors {
+                match self.doors.get(&(room.clone(), door.clone())) {
+                    Some(next) if *next != self.here && !previous.contains_key(next) => {
+                        previous.insert(next.clone(), (room.clone(), door.clone()));
+                        queue.push_back(next.clone());
+                    }
+                    _ => {}
+                }
+            }
         }
+        None
     }
 
-    fn jif(&mut self, operation: Operation) {
-        let parameter1: i64 = self.get_first_parameter(operation.first_parameter_mode);
-        let parameter2: i64 = self.get_second_parameter(operation.second_parameter_mode);
-
-        if parameter1 == 0 {
-            self.pointer = parameter2;
-        } else {
-            self.pointer += 3;
+    // Step on the plate with subsets of the carried items. Any superset of
+    // a set that is too heavy is too heavy as well, and any subset of one
+    // that is too light is too light, so those are never tried.
+    fn solve_weight(&mut self, door: &str) -> Result<(), IntcodeError> {
+        let items: Vec<String> = self.carried.clone();
+        if items.len() > MAX_ITEMS {
+            return Err(IntcodeError::NoSolution {
+                message: format!("too many items to weigh: {}", items.len()),
+            });
         }
-    }
-
-    fn lth(&mut self, operation: Operation) {
-        let parameter1: i64 = self.get_first_parameter(operation.first_parameter_mode);
-        let parameter2: i64 = self.get_second_parameter(operation.second_parameter_mode);
-
-        let result_index: i64 = match operation.third_parameter_mode {
-            ParameterMode::PositionMode => *self.program.entry(self.pointer + 3).or_insert(0),
-            ParameterMode::RelativeMode => {
-                self.relative_base + *self.program.entry(self.pointer + 3).or_insert(0)
+        let mut holding: u64 = (1 << items.len()) - 1;
+        let mut too_heavy: Vec<u64> = vec![];
+        let mut too_light: Vec<u64> = vec![];
+        let mut selections: Vec<u64> = (0..1 << items.len()).collect();
+        selections.sort_by_key(|selection| selection.count_ones());
+
+        for selection in selections {
+            if too_heavy.iter().any(|heavy| selection & heavy == *heavy)
+                || too_light.iter().any(|light| selection & light == selection)
+            {
+                continue;
             }
-            _ => panic!(
-                "Incorrect third parameter mode: {:?}",
-                operation.third_parameter_mode
-            ),
-        };
-
-        if parameter1 < parameter2 {
-            self.program.insert(result_index, 1);
-        } else {
-            self.program.insert(result_index, 0);
-        }
-        self.pointer += 4;
-    }
-
-    fn eql(&mut self, operation: Operation) {
-        let parameter1: i64 = self.get_first_parameter(operation.first_parameter_mode);
-        let parameter2: i64 = self.get_second_parameter(operation.second_parameter_mode);
-
-        let result_index: i64 = match operation.third_parameter_mode {
-            ParameterMode::PositionMode => *self.program.entry(self.pointer + 3).or_insert(0),
-            ParameterMode::RelativeMode => {
-                self.relative_base + *self.program.entry(self.pointer + 3).or_insert(0)
+            for (index, item) in items.iter().enumerate() {
+                let bit: u64 = 1 << index;
+                if holding & bit != 0 && selection & bit == 0 {
+                    self.command(&format!("drop {}", item))?;
+                } else if holding & bit == 0 && selection & bit != 0 {
+                    self.command(&format!("take {}", item))?;
+                }
             }
-            _ => panic!(
-                "Incorrect third parameter mode: {:?}",
-                operation.third_parameter_mode
-            ),
-        };
+            holding = selection;
 
-        if parameter1 == parameter2 {
-            self.program.insert(result_index, 1);
-        } else {
-            self.program.insert(result_index, 0);
+            let text: String = self.command(door)?;
+            if self.computer.is_halted() {
+                return Ok(());
+            }
+            if text.contains("lighter than the detected") {
+                too_heavy.push(selection);
+            } else if text.contains("heavier than the detected") {
+                too_light.push(selection);
+            }
         }
-        self.pointer += 4;
-    }
-
-    fn arb(&mut self, operation: Operation) {
-        let parameter1: i64 = self.get_first_parameter(operation.first_parameter_mode);
-        self.relative_base += parameter1;
-
-        self.pointer += 2;
+        Ok(())
     }
 }
 
-fn parse(filename: &str) -> HashMap<i64, i64> {
-    // read file
-    let data = fs::read_to_string(filename).expect(&format!("File not found: {filename}"));
-
-    // convert content into a vector of integers
-    let vec_data: Vec<i64> = data
-        .split(",")
-        .map(|x| x.trim().parse::<i64>().unwrap())
-        .collect();
-
-    let mut program: HashMap<i64, i64> = HashMap::new();
-
-    for (index, value) in vec_data.iter().enumerate() {
-        program.insert(index as i64, *value);
+fn solution(filename: &str) -> Result<i64, IntcodeError> {
+    let mut computer = AsciiComputer::new(parse(filename)?);
+    let start: Option<Room> = parse_room(&computer.read_until_prompt()?);
+    let mut explorer = Explorer::new(computer);
+    if let Some(start) = start {
+        explorer.explore(start)?;
     }
 
-    program
-}
-
-// Use for play interactively and obtain pre_commands
-//
-// fn read_command() -> String {
-//     let mut buffer = String::new();
-//     let stdin = io::stdin();
-//     let _ = stdin.read_line(&mut buffer);
-//     buffer
-// }
-
-fn solution(filename: &str) -> i32 {
-    let program = parse(filename);
-    let mut computer = IntcodeComputer {
-        program: program.clone(),
-        pointer: 0,
-        halted: false,
-        relative_base: 0,
-    };
-
-    let mut input: VecDeque<i64> = VecDeque::new();
-
-    let pre_commands = [
-        "north",
-        "north",
-        "east",
-        "east",
-        "take cake",
-        "west",
-        "west",
-        "south",
-        "south",
-        "south",
-        "west",
-        "take fuel cell",
-        "west",
-        "take easter egg",
-        "inv",
-        "east",
-        "east",
-        "north",
-        "east",
-        "take ornament",
-        "east",
-        "take hologram",
-        "east",
-        "take dark matter",
-        "north",
-        "north",
-        "east",
-        "take klein bottle",
-        "north",
-        "take hypercube",
-        "north",
-        "drop ornament",
-        "drop easter egg",
-        "drop hypercube",
-        "drop hologram",
-        "drop cake",
-        "drop fuel cell",
-        "drop dark matter",
-        "drop klein bottle",
-    ];
-
-    let _ = computer.run(&mut input);
-    for command in pre_commands {
-        input.clear();
-        for ascii in command.chars() {
-            input.push_back(ascii as i64);
+    if let Some((checkpoint, door)) = explorer.checkpoint.clone() {
+        for step in explorer.path_to(&checkpoint).unwrap_or_default() {
+            explorer.command(&step)?;
         }
-        input.push_back(10);
-
-        println!("--------------> {:?}", command);
-        let _ = computer.run(&mut input);
+        explorer.here = checkpoint;
+        explorer.solve_weight(&door)?;
     }
+    explorer.password.ok_or(IntcodeError::NoSolution {
+        message: "the droid never got through the pressure-sensitive floor".to_string(),
+    })
+}
 
-    let items = [
-        "ornament",
-        "easter egg",
-        "hypercube",
-        "hologram",
-        "cake",
-        "fuel cell",
-        "dark matter",
-        "klein bottle",
-    ];
-
-    let mut taken_items: Vec<String> = vec![];
-    'outer: for selection in 0..256 {
-        taken_items.clear();
-        for tp in 0..8 {
-            if selection & (1 << tp) != 0 {
-                // println!("s {}, tp {}", selection, tp);
-                let mut cmd = "take ".to_string();
-                cmd.push_str(items[tp].clone());
-                cmd.push_str("\n");
-                taken_items.push(cmd);
-            }
-        }
-        taken_items.push("west\n".to_string());
-
-        input.clear();
-        for command in &taken_items {
-            for ascii in command.chars() {
-                input.push_back(ascii as i64);
-            }
-        }
-
-        let output: Vec<i64> = computer.run(&mut input);
+fn main() -> Result<(), IntcodeError> {
+    println!("{:?}", solution("./input.txt")?); // 1090617344
+    Ok(())
+}
 
-        let mut message: String = String::new();
-        for c in output {
-            match char::from_u32(c as u32) {
-                Some(c) => message.push(c),
-                None => print!(" error {}", c),
-            }
-        }
-        if !message.contains(&"heavier") && !message.contains(&"lighter") {
-            println!("{}", message);
-            break 'outer;
-        }
-        for cmd in &mut taken_items {
-            *cmd = cmd.replace("take", "drop");
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_room_the_droid_ends_up_in() {
+        let text: &str = "\n\n\n== Pressure-Sensitive Floor ==\nAnalyzing...\n\nDoors here lead:\n- east\n\nA loud, robotic voice says \"Alert! Droids on this ship are lighter than the detected value!\" and you are ejected back to the checkpoint.\n\n\n\n== Security Checkpoint ==\nIn the next room, a pressure-sensitive floor will verify your identity.\n\nDoors here lead:\n- north\n- west\n\nItems here:\n- cake\n\nCommand?\n";
+        assert_eq!(
+            parse_room(text),
+            Some(Room {
+                name: "Security Checkpoint".to_string(),
+                doors: vec!["north".to_string(), "west".to_string()],
+                items: vec!["cake".to_string()],
+            })
+        );
+        assert_eq!(parse_room("You can't go that way.\n\nCommand?\n"), None);
+    }
 
-        input.clear();
-        for command in &taken_items {
-            for ascii in command.chars() {
-                input.push_back(ascii as i64);
-            }
-            input.push_back(10);
-        }
+    #[test]
+    fn finds_the_password() {
+        assert_eq!(
+            password("\"Oh, hello! You should be able to get in by typing 1090617344 on the keypad at the main airlock.\""),
+            Some(1090617344)
+        );
+    }
 
-        let _ = computer.run(&mut input);
+    #[test]
+    fn gives_up_on_impossible_ships() {
+        // keeps asking for commands and never answers
+        let listener = || AsciiComputer::new(intcode::Memory::new(vec![3, 5, 1105, 1, 0, 0]));
+        let explorer = Explorer::new(listener());
+        let closet = Room {
+            name: "Closet".to_string(),
+            doors: vec![],
+            items: vec!["mop".to_string()],
+        };
+        assert!(matches!(
+            explorer.is_safe("mop", &closet),
+            Err(IntcodeError::NoSolution { .. })
+        ));
+
+        let mut explorer = Explorer::new(listener());
+        explorer.carried = (0..=MAX_ITEMS).map(|item| item.to_string()).collect();
+        assert!(matches!(
+            explorer.solve_weight("north"),
+            Err(IntcodeError::NoSolution { .. })
+        ));
     }
-    0
-}
 
-fn main() {
-    println!("{:?}", solution("./input.txt")); // 1090617344
+    #[test]
+    fn gets_through_the_checkpoint() {
+        assert_eq!(
+            solution(concat!(env!("CARGO_MANIFEST_DIR"), "/input.txt")),
+            Ok(1090617344)
+        );
+    }
 }
@@ -0,0 +1,56 @@
+// Interpreter vs Cranelift JIT on day09 BOOST in sensor boost mode (input
+// 2), the longest running Intcode program in the repo.
+//
+// Run with `cargo bench --features jit --bench jit`. JIT times include
+// compiling every block, since each run starts from a fresh computer.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::time::{Duration, Instant};
+
+use intcode::{parse_values, IntcodeComputer, JitComputer, Memory};
+
+const ROUNDS: u32 = 10;
+
+fn measure(run: impl Fn() -> Vec<i64>) -> (Duration, Vec<i64>) {
+    let mut best: Duration = Duration::MAX;
+    let mut output: Vec<i64> = Vec::new();
+    for _ in 0..ROUNDS {
+        let start: Instant = Instant::now();
+        output = run();
+        best = best.min(start.elapsed());
+    }
+    (best, output)
+}
+
+fn main() {
+    let filename = format!("{}/../day09/input.txt", env!("CARGO_MANIFEST_DIR"));
+    let data =
+        fs::read_to_string(&filename).unwrap_or_else(|_| panic!("File not found: {filename}"));
+    let program: Memory = Memory::new(parse_values(&data).unwrap());
+
+    let (interpreted, expected) = measure(|| {
+        let mut computer = IntcodeComputer::new(program.clone());
+        computer.run(&mut VecDeque::from([2])).unwrap()
+    });
+    let (jit, output) = measure(|| {
+        let mut computer = JitComputer::new(program.clone()).unwrap();
+        computer.run(&mut VecDeque::from([2])).unwrap()
+    });
+    assert_eq!(output, expected);
+
+    let mut computer = JitComputer::new(program.clone()).unwrap();
+    computer.run(&mut VecDeque::from([2])).unwrap();
+    println!(
+        "{:<12} {:>12} {:>12} {:>8}",
+        "program", "interpreter", "jit", "speedup"
+    );
+    println!(
+        "{:<12} {:>12?} {:>12?} {:>7.1}x",
+        "day09 BOOST",
+        interpreted,
+        jit,
+        interpreted.as_secs_f64() / jit.as_secs_f64()
+    );
+    println!("{} blocks compiled", computer.compiled_blocks());
+}
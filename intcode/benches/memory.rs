@@ -0,0 +1,135 @@
+// Paged memory vs the old HashMap memory on real puzzle programs.
+//
+// Run with `cargo bench --bench memory`. The baseline is a copy of the
+// interpreter every day used before, with its HashMap<i64, i64> memory.
+
+#[path = "memory/hashmap.rs"]
+mod hashmap;
+
+use std::collections::VecDeque;
+use std::fs;
+use std::time::{Duration, Instant};
+
+use hashmap::HashMapComputer;
+use intcode::{parse_values, IntcodeComputer, Memory};
+
+const ROUNDS: u32 = 5;
+
+// What the workloads need from a computer, so both can run them.
+trait Machine: Clone {
+    fn run(&mut self, input: &mut VecDeque<i64>) -> Vec<i64>;
+}
+
+impl Machine for IntcodeComputer {
+    fn run(&mut self, input: &mut VecDeque<i64>) -> Vec<i64> {
+        IntcodeComputer::run(self, input).unwrap()
+    }
+}
+
+impl Machine for HashMapComputer {
+    fn run(&mut self, input: &mut VecDeque<i64>) -> Vec<i64> {
+        HashMapComputer::run(self, input)
+    }
+}
+
+fn load(day: &str) -> Vec<i64> {
+    let filename = format!("{}/../{}/input.txt", env!("CARGO_MANIFEST_DIR"), day);
+    let data =
+        fs::read_to_string(&filename).unwrap_or_else(|_| panic!("File not found: {filename}"));
+    parse_values(&data).unwrap()
+}
+
+// day19: a fresh computer for every probe of the 50x50 scan
+fn tractor_beam<M: Machine>(booted: &M) -> i64 {
+    let mut pulled: i64 = 0;
+    for x in 0..50 {
+        for y in 0..50 {
+            let mut computer: M = booted.clone();
+            pulled += computer.run(&mut VecDeque::from([x, y]))[0];
+        }
+    }
+    pulled
+}
+
+// day23: 50 NICs until the first packet is sent to address 255
+fn network<M: Machine>(booted: &M) -> i64 {
+    let mut computers: Vec<M> = vec![booted.clone(); 50];
+    let mut buffers: Vec<VecDeque<i64>> =
+        (0..50).map(|address| VecDeque::from([address])).collect();
+
+    loop {
+        for index in 0..50 {
+            if buffers[index].is_empty() {
+                buffers[index].push_back(-1);
+            }
+            let output: Vec<i64> = computers[index].run(&mut buffers[index]);
+            for packet in output.chunks(3) {
+                if packet[0] == 255 {
+                    return packet[2];
+                }
+                buffers[packet[0] as usize].extend(&packet[1..]);
+            }
+        }
+    }
+}
+
+// day25: walk back and forth between the first rooms
+fn adventure<M: Machine>(booted: &M) -> i64 {
+    let mut computer: M = booted.clone();
+    let mut characters: i64 = computer.run(&mut VecDeque::new()).len() as i64;
+
+    for _ in 0..20 {
+        for command in ["north\n", "south\n", "inv\n"] {
+            let mut input: VecDeque<i64> = command.chars().map(|c| c as i64).collect();
+            characters += computer.run(&mut input).len() as i64;
+        }
+    }
+    characters
+}
+
+fn measure(workload: impl Fn() -> i64) -> (Duration, i64) {
+    let mut best: Duration = Duration::MAX;
+    let mut result: i64 = 0;
+    for _ in 0..ROUNDS {
+        let start: Instant = Instant::now();
+        result = workload();
+        best = best.min(start.elapsed());
+    }
+    (best, result)
+}
+
+type Workloads = (
+    &'static str,
+    fn(&HashMapComputer) -> i64,
+    fn(&IntcodeComputer) -> i64,
+);
+
+fn main() {
+    let workloads: [Workloads; 3] = [
+        ("day19_part1", tractor_beam, tractor_beam),
+        ("day23_part1", network, network),
+        ("day25_part1", adventure, adventure),
+    ];
+
+    println!(
+        "{:<12} {:>12} {:>12} {:>8}",
+        "program", "hashmap", "paged", "speedup"
+    );
+    for (day, on_hashmap, on_paged) in workloads {
+        let values: Vec<i64> = load(day);
+        let old = HashMapComputer::new(&values);
+        let paged = IntcodeComputer::new(Memory::new(values));
+
+        let (hashmap_time, hashmap_result) = measure(|| on_hashmap(&old));
+        let (paged_time, paged_result) = measure(|| on_paged(&paged));
+        assert_eq!(hashmap_result, paged_result);
+
+        println!(
+            "{:<12} {:>12?} {:>12?} {:>7.1}x",
+            day,
+            hashmap_time,
+            paged_time,
+            hashmap_time.as_secs_f64() / paged_time.as_secs_f64()
+        );
+    }
+}
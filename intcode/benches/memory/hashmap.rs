@@ -0,0 +1,105 @@
+// The interpreter as it was before paged memory: every cell lives in a
+// HashMap<i64, i64>, and reads go through `entry().or_insert(0)` like the
+// days did. Only here as the baseline for the memory benchmark.
+
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Debug, Clone)]
+pub struct HashMapComputer {
+    program: HashMap<i64, i64>,
+    pointer: i64,
+    relative_base: i64,
+    halted: bool,
+}
+
+impl HashMapComputer {
+    pub fn new(values: &[i64]) -> Self {
+        HashMapComputer {
+            program: (0..).zip(values.iter().copied()).collect(),
+            pointer: 0,
+            relative_base: 0,
+            halted: false,
+        }
+    }
+
+    fn read(&mut self, address: i64) -> i64 {
+        *self.program.entry(address).or_insert(0)
+    }
+
+    fn write(&mut self, address: i64, value: i64) {
+        self.program.insert(address, value);
+    }
+
+    fn parameter(&mut self, instruction: i64, offset: i64) -> i64 {
+        let parameter: i64 = self.read(self.pointer + offset);
+        match instruction / [100, 1000, 10000][offset as usize - 1] % 10 {
+            0 => self.read(parameter),
+            1 => parameter,
+            2 => self.read(self.relative_base + parameter),
+            mode => panic!("Unknown parameter mode: {}", mode),
+        }
+    }
+
+    fn result_index(&mut self, instruction: i64, offset: i64) -> i64 {
+        let parameter: i64 = self.read(self.pointer + offset);
+        match instruction / [100, 1000, 10000][offset as usize - 1] % 10 {
+            0 => parameter,
+            2 => self.relative_base + parameter,
+            mode => panic!("Incorrect write parameter mode: {}", mode),
+        }
+    }
+
+    // Run until the program halts, or until it asks for input and `input` is
+    // empty. Everything written by OUT in the meantime is returned.
+    pub fn run(&mut self, input: &mut VecDeque<i64>) -> Vec<i64> {
+        let mut output: Vec<i64> = Vec::new();
+        while !self.halted {
+            let instruction: i64 = self.read(self.pointer);
+            match instruction % 100 {
+                1 | 2 | 7 | 8 => {
+                    let a: i64 = self.parameter(instruction, 1);
+                    let b: i64 = self.parameter(instruction, 2);
+                    let result_index: i64 = self.result_index(instruction, 3);
+                    let value: i64 = match instruction % 100 {
+                        1 => a.wrapping_add(b),
+                        2 => a.wrapping_mul(b),
+                        7 => (a < b) as i64,
+                        _ => (a == b) as i64,
+                    };
+                    self.write(result_index, value);
+                    self.pointer += 4;
+                }
+                3 => {
+                    let value: i64 = match input.pop_front() {
+                        Some(value) => value,
+                        None => break,
+                    };
+                    let result_index: i64 = self.result_index(instruction, 1);
+                    self.write(result_index, value);
+                    self.pointer += 2;
+                }
+                4 => {
+                    let value: i64 = self.parameter(instruction, 1);
+                    output.push(value);
+                    self.pointer += 2;
+                }
+                5 | 6 => {
+                    let a: i64 = self.parameter(instruction, 1);
+                    let b: i64 = self.parameter(instruction, 2);
+                    if (a != 0) == (instruction % 100 == 5) {
+                        self.pointer = b;
+                    } else {
+                        self.pointer += 3;
+                    }
+                }
+                9 => {
+                    self.relative_base += self.parameter(instruction, 1);
+                    self.pointer += 2;
+                }
+                99 => self.halted = true,
+                operation => panic!("Unknown operation: {}", operation),
+            }
+        }
+        output
+    }
+}
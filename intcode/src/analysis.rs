@@ -0,0 +1,457 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use crate::computer::{OperationType, ParameterMode};
+use crate::disassembler::{decode, find_code, moved_constant, Instruction};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EdgeKind {
+    // execution runs on into the next block
+    FallThrough,
+    // conditional jump taken
+    Branch,
+    // unconditional jump
+    Jump,
+    // unconditional jump into a subroutine
+    Call,
+    // where a call comes back to, the address it pushed before jumping
+    Return,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Edge {
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub start: usize,
+    pub instructions: Vec<Instruction>,
+    pub successors: Vec<Edge>,
+}
+
+impl Block {
+    pub fn last(&self) -> &Instruction {
+        self.instructions.last().unwrap()
+    }
+}
+
+// Instruction at `address` writes into the code cell `target`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelfModifyingWrite {
+    pub address: usize,
+    pub target: usize,
+}
+
+// Code reached through the relative-base stack convention: the caller
+// stores a return address at [rb+0] and jumps to an entry that starts with
+// `rbo #frame`; the callee leaves with `rbo #-frame` and `jz #0, [rb+0]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subroutine {
+    pub entry: usize,
+    pub frame: i64,
+    // starts of the blocks that belong to the subroutine
+    pub blocks: BTreeSet<usize>,
+    // blocks ending with a call to the entry
+    pub callers: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ControlFlowGraph {
+    pub blocks: BTreeMap<usize, Block>,
+    // jumps whose target is only known at run time
+    pub indirect_jumps: Vec<usize>,
+    pub self_modifying_writes: Vec<SelfModifyingWrite>,
+    pub subroutines: BTreeMap<usize, Subroutine>,
+}
+
+fn is_unconditional(instruction: &Instruction) -> bool {
+    instruction.jump_target().is_some() && instruction.ends_flow()
+}
+
+fn written_address(instruction: &Instruction) -> Option<usize> {
+    let parameter: usize = match instruction.operation.operation {
+        OperationType::SUM | OperationType::MUL | OperationType::LTH | OperationType::EQL => 3,
+        OperationType::CPY => 1,
+        _ => return None,
+    };
+    let value: i64 = instruction.parameters[parameter - 1];
+    if instruction.operation.parameter_mode(parameter) == ParameterMode::PositionMode && value >= 0
+    {
+        Some(value as usize)
+    } else {
+        None
+    }
+}
+
+fn frame_size(instruction: &Instruction) -> Option<i64> {
+    match (
+        instruction.operation.operation,
+        instruction.operation.first_parameter_mode,
+    ) {
+        (OperationType::ARB, ParameterMode::ImmediateMode) if instruction.parameters[0] > 0 => {
+            Some(instruction.parameters[0])
+        }
+        _ => None,
+    }
+}
+
+pub fn analyse(values: &[i64]) -> ControlFlowGraph {
+    let (starts, _) = find_code(values);
+    let instructions: BTreeMap<usize, Instruction> = (0..values.len())
+        .filter(|address| starts[*address])
+        .map(|address| (address, decode(values, address).unwrap()))
+        .collect();
+    let in_code = |address: i64| address >= 0 && instructions.contains_key(&(address as usize));
+
+    // blocks start at the entry point, jump targets, addresses code stores
+    // as constants and after every jump
+    let mut leaders: BTreeSet<usize> = BTreeSet::from([0]);
+    for (address, instruction) in &instructions {
+        let targets = instruction.jump_target().into_iter();
+        for target in targets.chain(moved_constant(instruction)) {
+            if in_code(target) {
+                leaders.insert(target as usize);
+            }
+        }
+        if matches!(
+            instruction.operation.operation,
+            OperationType::JIT | OperationType::JIF | OperationType::END
+        ) {
+            leaders.insert(address + instruction.size());
+        }
+    }
+
+    let mut graph = ControlFlowGraph::default();
+    let mut current: Option<Block> = None;
+    for (address, instruction) in &instructions {
+        let continues: bool = match &current {
+            Some(block) => {
+                !leaders.contains(address) && block.last().address + block.last().size() == *address
+            }
+            None => false,
+        };
+        if !continues {
+            if let Some(block) = current.take() {
+                graph.blocks.insert(block.start, block);
+            }
+            current = Some(Block {
+                start: *address,
+                instructions: Vec::new(),
+                successors: Vec::new(),
+            });
+        }
+        current
+            .as_mut()
+            .unwrap()
+            .instructions
+            .push(instruction.clone());
+    }
+    if let Some(block) = current {
+        graph.blocks.insert(block.start, block);
+    }
+
+    // writes into cells covered by an instruction
+    for (address, instruction) in &instructions {
+        let target: Option<usize> = written_address(instruction).filter(|target| {
+            instructions
+                .range(..=*target)
+                .next_back()
+                .is_some_and(|(start, covering)| *target < start + covering.size())
+        });
+        if let Some(target) = target {
+            graph.self_modifying_writes.push(SelfModifyingWrite {
+                address: *address,
+                target,
+            });
+        }
+    }
+
+    // an rbo that code falls into, like the one at address 0, sets up the
+    // stack rather than a frame
+    let fallen_into: BTreeSet<usize> = graph
+        .blocks
+        .values()
+        .map(|block| block.last())
+        .filter(|last| !last.ends_flow())
+        .map(|last| last.address + last.size())
+        .collect();
+    for (start, block) in graph.blocks.range(1..) {
+        if fallen_into.contains(start) {
+            continue;
+        }
+        if let Some(frame) = block.instructions.first().and_then(frame_size) {
+            graph.subroutines.insert(
+                *start,
+                Subroutine {
+                    entry: *start,
+                    frame,
+                    blocks: BTreeSet::new(),
+                    callers: Vec::new(),
+                },
+            );
+        }
+    }
+
+    // edges
+    let entries: BTreeSet<usize> = graph.subroutines.keys().copied().collect();
+    for block in graph.blocks.values_mut() {
+        let last: &Instruction = block.last();
+        let next: usize = last.address + last.size();
+        let mut successors: Vec<Edge> = Vec::new();
+
+        match last.jump_target().filter(|target| in_code(*target)) {
+            Some(target) if is_unconditional(last) && entries.contains(&(target as usize)) => {
+                successors.push(Edge {
+                    to: target as usize,
+                    kind: EdgeKind::Call,
+                });
+                let pushed: Option<i64> = block
+                    .instructions
+                    .iter()
+                    .rev()
+                    .find_map(moved_constant)
+                    .filter(|address| in_code(*address));
+                if let Some(address) = pushed {
+                    successors.push(Edge {
+                        to: address as usize,
+                        kind: EdgeKind::Return,
+                    });
+                }
+            }
+            Some(target) if is_unconditional(last) => successors.push(Edge {
+                to: target as usize,
+                kind: EdgeKind::Jump,
+            }),
+            Some(target) => successors.push(Edge {
+                to: target as usize,
+                kind: EdgeKind::Branch,
+            }),
+            None if matches!(
+                last.operation.operation,
+                OperationType::JIT | OperationType::JIF
+            ) =>
+            {
+                graph.indirect_jumps.push(last.address);
+                // a call through a function pointer still pushes where it returns to
+                let pushed: Option<i64> = block
+                    .instructions
+                    .iter()
+                    .rev()
+                    .filter(|instruction| {
+                        instruction.operation.third_parameter_mode == ParameterMode::RelativeMode
+                            && instruction.parameters[2] == 0
+                    })
+                    .find_map(moved_constant)
+                    .filter(|address| last.ends_flow() && in_code(*address));
+                if let Some(address) = pushed {
+                    successors.push(Edge {
+                        to: address as usize,
+                        kind: EdgeKind::Return,
+                    });
+                }
+            }
+            None => {}
+        }
+        if !last.ends_flow() && instructions.contains_key(&next) {
+            successors.push(Edge {
+                to: next,
+                kind: EdgeKind::FallThrough,
+            });
+        }
+        block.successors = successors;
+    }
+
+    // subroutine bodies: everything reachable from the entry without
+    // following calls into other subroutines
+    let bodies: Vec<BTreeSet<usize>> = graph
+        .subroutines
+        .keys()
+        .map(|entry| graph.region(*entry))
+        .collect();
+    for (subroutine, blocks) in graph.subroutines.values_mut().zip(bodies) {
+        subroutine.blocks = blocks;
+        subroutine.callers = graph
+            .blocks
+            .values()
+            .filter(|block| {
+                block.successors.contains(&Edge {
+                    to: subroutine.entry,
+                    kind: EdgeKind::Call,
+                })
+            })
+            .map(|block| block.start)
+            .collect();
+    }
+    graph
+}
+
+impl ControlFlowGraph {
+    // Starts of the blocks reachable from `entry` without following calls.
+    pub fn region(&self, entry: usize) -> BTreeSet<usize> {
+        let mut region: BTreeSet<usize> = BTreeSet::new();
+        let mut queue: VecDeque<usize> = VecDeque::from([entry]);
+        while let Some(start) = queue.pop_front() {
+            if !self.blocks.contains_key(&start) || !region.insert(start) {
+                continue;
+            }
+            for edge in &self.blocks[&start].successors {
+                if edge.kind != EdgeKind::Call {
+                    queue.push_back(edge.to);
+                }
+            }
+        }
+        region
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut dot: String = String::from("digraph intcode {\n");
+        dot.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+
+        let node = |block: &Block| {
+            let lines: Vec<String> = block
+                .instructions
+                .iter()
+                .map(|instruction| format!("{}: {}\\l", instruction.address, instruction))
+                .collect();
+            let modified: bool = self
+                .self_modifying_writes
+                .iter()
+                .any(|write| write.address >= block.start && write.address <= block.last().address);
+            format!(
+                "b{} [label=\"{}\"{}];\n",
+                block.start,
+                lines.concat(),
+                if modified { ", color=red" } else { "" }
+            )
+        };
+
+        let mut placed: BTreeSet<usize> = BTreeSet::new();
+        for subroutine in self.subroutines.values() {
+            dot.push_str(&format!("    subgraph cluster_{} {{\n", subroutine.entry));
+            dot.push_str(&format!(
+                "        label=\"sub {} (frame {})\";\n",
+                subroutine.entry, subroutine.frame
+            ));
+            for start in &subroutine.blocks {
+                if placed.insert(*start) {
+                    dot.push_str(&format!("        {}", node(&self.blocks[start])));
+                }
+            }
+            dot.push_str("    }\n");
+        }
+        for block in self.blocks.values() {
+            if !placed.contains(&block.start) {
+                dot.push_str(&format!("    {}", node(block)));
+            }
+        }
+
+        for block in self.blocks.values() {
+            for edge in &block.successors {
+                let style: &str = match edge.kind {
+                    EdgeKind::FallThrough => "",
+                    EdgeKind::Branch => " [label=\"taken\"]",
+                    EdgeKind::Jump => " [style=bold]",
+                    EdgeKind::Call => " [label=\"call\", color=blue]",
+                    EdgeKind::Return => " [style=dashed]",
+                };
+                dot.push_str(&format!("    b{} -> b{}{};\n", block.start, edge.to, style));
+            }
+        }
+        for write in &self.self_modifying_writes {
+            if let Some((start, _)) = self.blocks.range(..=write.target).next_back() {
+                dot.push_str(&format!(
+                    "    b{} -> b{} [label=\"writes {}\", style=dotted, color=red];\n",
+                    self.blocks.range(..=write.address).next_back().unwrap().0,
+                    start,
+                    write.target
+                ));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assemble;
+
+    const PROGRAM: &str = "
+                rbo #stack
+                in [rb+1]
+                add #back, #0, [rb+0]
+                jz #0, #double
+        back:   out [rb+1]
+                add #5, #0, [patch+1]
+        patch:  out #0
+                hlt
+        double: rbo #2
+                mul [rb-1], #2, [rb-1]
+                jz [rb-1], #zero
+                rbo #-2
+                jz #0, [rb+0]
+        zero:   hlt
+        stack:  .data 0, 0
+    ";
+
+    #[test]
+    fn splits_blocks_and_finds_edges() {
+        let values: Vec<i64> = assemble(PROGRAM).unwrap();
+        let graph: ControlFlowGraph = analyse(&values);
+
+        let starts: Vec<usize> = graph.blocks.keys().copied().collect();
+        assert_eq!(starts, vec![0, 11, 20, 29, 34]);
+        assert_eq!(
+            graph.blocks[&0].successors,
+            vec![
+                Edge {
+                    to: 20,
+                    kind: EdgeKind::Call
+                },
+                Edge {
+                    to: 11,
+                    kind: EdgeKind::Return
+                }
+            ]
+        );
+        assert_eq!(
+            graph.blocks[&20].successors,
+            vec![
+                Edge {
+                    to: 34,
+                    kind: EdgeKind::Branch
+                },
+                Edge {
+                    to: 29,
+                    kind: EdgeKind::FallThrough
+                }
+            ]
+        );
+        assert_eq!(graph.indirect_jumps, vec![31]);
+    }
+
+    #[test]
+    fn finds_subroutines_and_self_modification() {
+        let values: Vec<i64> = assemble(PROGRAM).unwrap();
+        let graph: ControlFlowGraph = analyse(&values);
+
+        let subroutine: &Subroutine = &graph.subroutines[&20];
+        assert_eq!(subroutine.frame, 2);
+        assert_eq!(subroutine.blocks, BTreeSet::from([20, 29, 34]));
+        assert_eq!(subroutine.callers, vec![0]);
+        assert_eq!(
+            graph.self_modifying_writes,
+            vec![SelfModifyingWrite {
+                address: 13,
+                target: 18
+            }]
+        );
+
+        let dot: String = graph.to_dot();
+        assert!(dot.starts_with("digraph intcode {"));
+        assert!(dot.contains("subgraph cluster_20"));
+        assert!(dot.contains("b0 -> b20 [label=\"call\", color=blue];"));
+    }
+}
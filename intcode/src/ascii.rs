@@ -0,0 +1,169 @@
+use std::collections::VecDeque;
+
+use crate::computer::{IntcodeComputer, StepResult};
+use crate::error::IntcodeError;
+use crate::memory::Memory;
+
+// Largest value that is still read as a character.
+const MAX_ASCII: i64 = 127;
+
+// Wraps a computer running a text program: lines go in as ASCII codes
+// ending in a newline, and output comes back as text. Output values past
+// the ASCII range are the program's answers and are kept apart.
+#[derive(Debug, Clone)]
+pub struct AsciiComputer {
+    computer: IntcodeComputer,
+    input: VecDeque<i64>,
+    answers: Vec<i64>,
+}
+
+impl AsciiComputer {
+    pub fn new(program: Memory) -> Self {
+        AsciiComputer {
+            computer: IntcodeComputer::new(program),
+            input: VecDeque::new(),
+            answers: Vec::new(),
+        }
+    }
+
+    pub fn computer(&self) -> &IntcodeComputer {
+        &self.computer
+    }
+
+    // e.g. to patch the program before it starts
+    pub fn computer_mut(&mut self) -> &mut IntcodeComputer {
+        &mut self.computer
+    }
+
+    // Independent copy to try something out on, e.g. picking up an item
+    // that might end the game.
+    pub fn fork(&self) -> Self {
+        self.clone()
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.computer.halted
+    }
+
+    // Queue `line` and a newline. Nothing runs until the next read.
+    pub fn send_line(&mut self, line: &str) -> Result<(), IntcodeError> {
+        if let Some(c) = line.chars().find(|c| !c.is_ascii()) {
+            return Err(IntcodeError::InvalidAscii { value: c as i64 });
+        }
+        self.input.extend(line.chars().map(|c| c as i64));
+        self.input.push_back(10);
+        Ok(())
+    }
+
+    pub fn send_lines(&mut self, lines: &[&str]) -> Result<(), IntcodeError> {
+        lines.iter().try_for_each(|line| self.send_line(line))
+    }
+
+    // Run until the program halts, or wants input once everything sent so
+    // far is used up, and return the text it printed on the way.
+    pub fn read_until_prompt(&mut self) -> Result<String, IntcodeError> {
+        let text: Option<String> = self.read(None)?;
+        Ok(text.unwrap_or_default())
+    }
+
+    // Same as `read_until_prompt`, but None once `max_steps` instructions
+    // have run without getting there, for programs that may never ask.
+    pub fn read_until_prompt_within(
+        &mut self,
+        max_steps: usize,
+    ) -> Result<Option<String>, IntcodeError> {
+        self.read(Some(max_steps))
+    }
+
+    fn read(&mut self, max_steps: Option<usize>) -> Result<Option<String>, IntcodeError> {
+        let mut text: String = String::new();
+        let mut steps: usize = 0;
+        while max_steps.is_none_or(|max_steps| steps < max_steps) {
+            steps += 1;
+            match self.computer.step(&mut self.input)? {
+                StepResult::Executed => continue,
+                StepResult::Output(value) if (0..=MAX_ASCII).contains(&value) => {
+                    text.push(value as u8 as char)
+                }
+                StepResult::Output(value) if value > MAX_ASCII => self.answers.push(value),
+                StepResult::Output(value) => return Err(IntcodeError::InvalidAscii { value }),
+                StepResult::NeedsInput | StepResult::Halted => return Ok(Some(text)),
+            }
+        }
+        Ok(None)
+    }
+
+    // most recent non-ASCII output
+    pub fn answer(&self) -> Option<i64> {
+        self.answers.last().copied()
+    }
+
+    pub fn answers(&self) -> &[i64] {
+        &self.answers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assemble;
+
+    // prints "?\n", echoes one line, then prints its length as an answer
+    // plus 1000
+    fn echo() -> AsciiComputer {
+        let values: Vec<i64> = assemble(
+            "
+                    out #63
+                    out #10
+            loop:   in [100]
+                    out [100]
+                    add [101], #1, [101]
+                    eq [100], #10, [102]
+                    jz [102], #loop
+                    add [101], #999, [101]
+                    out [101]
+                    hlt
+            ",
+        )
+        .unwrap();
+        AsciiComputer::new(Memory::new(values))
+    }
+
+    #[test]
+    fn separates_text_from_answers() {
+        let mut computer: AsciiComputer = echo();
+        assert_eq!(computer.read_until_prompt().unwrap(), "?\n");
+        assert!(!computer.is_halted());
+        computer.send_line("WALK").unwrap();
+        assert_eq!(computer.read_until_prompt().unwrap(), "WALK\n");
+        assert!(computer.is_halted());
+        assert_eq!(computer.answer(), Some(1004));
+    }
+
+    #[test]
+    fn gives_up_after_the_step_limit() {
+        let values: Vec<i64> = assemble("loop: out #46\njnz #1, #loop").unwrap();
+        let mut computer = AsciiComputer::new(Memory::new(values));
+        assert_eq!(computer.read_until_prompt_within(100), Ok(None));
+        assert_eq!(
+            echo().read_until_prompt_within(100),
+            Ok(Some("?\n".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_characters() {
+        let mut computer: AsciiComputer = echo();
+        assert_eq!(
+            computer.send_line("café"),
+            Err(IntcodeError::InvalidAscii { value: 233 })
+        );
+
+        let values: Vec<i64> = assemble("out #65\nout #-1\nhlt").unwrap();
+        let mut computer = AsciiComputer::new(Memory::new(values));
+        assert_eq!(
+            computer.read_until_prompt(),
+            Err(IntcodeError::InvalidAscii { value: -1 })
+        );
+    }
+}
@@ -0,0 +1,357 @@
+use std::collections::HashMap;
+
+use crate::computer::{OperationType, ParameterMode};
+use crate::error::IntcodeError;
+
+// Source format, one statement per line:
+//
+//     loop:  in [rb+1]              ; labels end with a colon
+//            jz [rb+1], #done       ; #imm, [addr] and [rb+off] operands
+//            .data 1, -2, loop+3    ; raw values, labels allowed
+//            .string "hi\n"         ; one value per character
+//     12:    hlt                    ; a leading number asserts the address
+//
+// This is the format `listing` prints, so a listing assembles back to the
+// exact image it was made from.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expression {
+    Number(i64),
+    Label { name: String, offset: i64 },
+}
+
+#[derive(Debug, Clone)]
+struct Operand {
+    parameter_mode: ParameterMode,
+    value: Expression,
+}
+
+#[derive(Debug, Clone)]
+enum Statement {
+    Instruction {
+        operation: OperationType,
+        operands: Vec<Operand>,
+    },
+    Data(Vec<Expression>),
+}
+
+impl Statement {
+    fn size(&self) -> usize {
+        match self {
+            Statement::Instruction { operands, .. } => 1 + operands.len(),
+            Statement::Data(values) => values.len(),
+        }
+    }
+}
+
+fn error(line: usize, message: String) -> IntcodeError {
+    IntcodeError::Assembly { line, message }
+}
+
+fn is_identifier(token: &str) -> bool {
+    let mut chars = token.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// Drop a `;` comment, leaving semicolons inside strings alone.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string: bool = false;
+    let mut escaped: bool = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            ';' if !in_string => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_expression(line: usize, text: &str) -> Result<Expression, IntcodeError> {
+    let text: &str = text.trim();
+    if let Ok(number) = text.parse::<i64>() {
+        return Ok(Expression::Number(number));
+    }
+
+    // label, label+offset or label-offset
+    let (name, offset) = match text.rfind(['+', '-']) {
+        Some(index) if index > 0 => {
+            let offset: i64 = text[index + 1..]
+                .trim()
+                .parse()
+                .map_err(|_| error(line, format!("invalid expression {:?}", text)))?;
+            let sign: i64 = if &text[index..=index] == "-" { -1 } else { 1 };
+            (text[..index].trim(), sign * offset)
+        }
+        _ => (text, 0),
+    };
+    if !is_identifier(name) {
+        return Err(error(line, format!("invalid expression {:?}", text)));
+    }
+    Ok(Expression::Label {
+        name: name.to_string(),
+        offset,
+    })
+}
+
+fn parse_operand(line: usize, text: &str) -> Result<Operand, IntcodeError> {
+    let text: &str = text.trim();
+    if let Some(value) = text.strip_prefix('#') {
+        return Ok(Operand {
+            parameter_mode: ParameterMode::ImmediateMode,
+            value: parse_expression(line, value)?,
+        });
+    }
+
+    let inner: &str = text
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+        .ok_or_else(|| error(line, format!("invalid operand {:?}", text)))?
+        .trim();
+    let relative: Option<&str> = inner.strip_prefix("rb").filter(|rest| {
+        let rest: &str = rest.trim_start();
+        rest.is_empty() || rest.starts_with('+') || rest.starts_with('-')
+    });
+
+    match relative {
+        None => Ok(Operand {
+            parameter_mode: ParameterMode::PositionMode,
+            value: parse_expression(line, inner)?,
+        }),
+        Some(offset) => {
+            let offset: &str = offset.trim();
+            let value: Expression = match offset.strip_prefix('-') {
+                _ if offset.is_empty() => Expression::Number(0),
+                Some(negative) => match parse_expression(line, negative)? {
+                    Expression::Number(number) => Expression::Number(-number),
+                    Expression::Label { .. } => {
+                        return Err(error(line, format!("cannot negate label in {:?}", text)))
+                    }
+                },
+                None => parse_expression(line, &offset[1..])?,
+            };
+            Ok(Operand {
+                parameter_mode: ParameterMode::RelativeMode,
+                value,
+            })
+        }
+    }
+}
+
+fn parse_string(line: usize, text: &str) -> Result<Vec<Expression>, IntcodeError> {
+    let inner: &str = text
+        .trim()
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .ok_or_else(|| error(line, format!("invalid string {}", text.trim())))?;
+
+    let mut values: Vec<Expression> = Vec::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        let c: char = match c {
+            '\\' => match chars.next() {
+                Some('n') => '\n',
+                Some('"') => '"',
+                Some('\\') => '\\',
+                other => return Err(error(line, format!("invalid escape {:?}", other))),
+            },
+            _ => c,
+        };
+        values.push(Expression::Number(c as i64));
+    }
+    Ok(values)
+}
+
+fn parse_statement(line: usize, text: &str) -> Result<Statement, IntcodeError> {
+    let (keyword, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let arguments: Vec<&str> = if rest.trim().is_empty() {
+        Vec::new()
+    } else {
+        rest.split(',').collect()
+    };
+
+    match keyword {
+        ".data" => Ok(Statement::Data(
+            arguments
+                .iter()
+                .map(|argument| parse_expression(line, argument))
+                .collect::<Result<_, _>>()?,
+        )),
+        ".string" => Ok(Statement::Data(parse_string(line, rest)?)),
+        _ => {
+            let operation: OperationType = OperationType::from_mnemonic(keyword)
+                .ok_or_else(|| error(line, format!("unknown mnemonic {:?}", keyword)))?;
+            if arguments.len() != operation.parameters() {
+                return Err(error(
+                    line,
+                    format!(
+                        "{} takes {} operands, found {}",
+                        keyword,
+                        operation.parameters(),
+                        arguments.len()
+                    ),
+                ));
+            }
+            Ok(Statement::Instruction {
+                operation,
+                operands: arguments
+                    .iter()
+                    .map(|argument| parse_operand(line, argument))
+                    .collect::<Result<_, _>>()?,
+            })
+        }
+    }
+}
+
+fn resolve(
+    line: usize,
+    expression: &Expression,
+    labels: &HashMap<String, usize>,
+) -> Result<i64, IntcodeError> {
+    match expression {
+        Expression::Number(number) => Ok(*number),
+        Expression::Label { name, offset } => labels
+            .get(name)
+            .map(|address| *address as i64 + offset)
+            .ok_or_else(|| error(line, format!("undefined label {:?}", name))),
+    }
+}
+
+pub fn assemble(source: &str) -> Result<Vec<i64>, IntcodeError> {
+    let mut labels: HashMap<String, usize> = HashMap::new();
+    let mut statements: Vec<(usize, Statement)> = Vec::new();
+    let mut address: usize = 0;
+
+    // first pass: parse and place labels
+    for (index, text) in source.lines().enumerate() {
+        let line: usize = index + 1;
+        let mut text: &str = strip_comment(text).trim();
+
+        while let Some((prefix, rest)) = text.split_once(':') {
+            let prefix: &str = prefix.trim();
+            if let Ok(expected) = prefix.parse::<usize>() {
+                if expected != address {
+                    return Err(error(
+                        line,
+                        format!("expected address {}, found {}", expected, address),
+                    ));
+                }
+            } else if is_identifier(prefix) {
+                if labels.insert(prefix.to_string(), address).is_some() {
+                    return Err(error(line, format!("duplicate label {:?}", prefix)));
+                }
+            } else {
+                break;
+            }
+            text = rest.trim();
+        }
+
+        if !text.is_empty() {
+            let statement: Statement = parse_statement(line, text)?;
+            address += statement.size();
+            statements.push((line, statement));
+        }
+    }
+
+    // second pass: encode
+    let mut values: Vec<i64> = Vec::with_capacity(address);
+    for (line, statement) in statements {
+        match statement {
+            Statement::Instruction {
+                operation,
+                operands,
+            } => {
+                let mut instruction: i64 = operation as i64;
+                let mut scale: i64 = 100;
+                for operand in &operands {
+                    instruction += operand.parameter_mode as i64 * scale;
+                    scale *= 10;
+                }
+                values.push(instruction);
+                for operand in &operands {
+                    values.push(resolve(line, &operand.value, &labels)?);
+                }
+            }
+            Statement::Data(expressions) => {
+                for expression in &expressions {
+                    values.push(resolve(line, expression, &labels)?);
+                }
+            }
+        }
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{listing, parse_values, IntcodeComputer, Memory};
+    use std::collections::VecDeque;
+    use std::fs;
+
+    fn run(source: &str, input: Vec<i64>) -> Vec<i64> {
+        let mut computer = IntcodeComputer::new(Memory::new(assemble(source).unwrap()));
+        computer.run(&mut VecDeque::from(input)).unwrap()
+    }
+
+    #[test]
+    fn assembles_operand_modes() {
+        assert_eq!(
+            assemble("add #3, [-4], [rb-2]\nmul [rb], [rb+7], [0]\nhlt").unwrap(),
+            vec![20101, 3, -4, -2, 2202, 0, 7, 0, 99]
+        );
+    }
+
+    #[test]
+    fn labels_and_directives() {
+        let source: &str = "
+            start:  rbo #stack          ; set up a stack
+                    in [rb+0]
+            loop:   out [rb+0]
+                    add [rb+0], #-1, [rb+0]
+                    jnz [rb+0], #loop
+                    hlt
+            text:   .string \"a;\\\"\"
+            stack:  .data 0
+        ";
+        assert_eq!(run(source, vec![3]), vec![3, 2, 1]);
+        assert_eq!(&assemble(source).unwrap()[14..17], &[97, 59, 34]);
+    }
+
+    #[test]
+    fn reports_source_errors() {
+        assert_eq!(
+            assemble("hlt\njnz [1], #nowhere").err(),
+            Some(IntcodeError::Assembly {
+                line: 2,
+                message: "undefined label \"nowhere\"".to_string()
+            })
+        );
+        assert!(assemble("add #1, #2").is_err());
+        assert!(assemble("nop").is_err());
+        assert!(assemble("5: hlt").is_err());
+    }
+
+    #[test]
+    fn disassembled_programs_reassemble_exactly() {
+        let days: [&str; 9] = [
+            "day05_part1",
+            "day07_part1",
+            "day09",
+            "day11_part1",
+            "day13_part1",
+            "day15_part1",
+            "day17_part1",
+            "day19_part1",
+            "day25_part1",
+        ];
+        for day in days {
+            let filename = format!("{}/../{}/input.txt", env!("CARGO_MANIFEST_DIR"), day);
+            let values: Vec<i64> = parse_values(&fs::read_to_string(filename).unwrap()).unwrap();
+            assert_eq!(assemble(&listing(&values)).unwrap(), values, "{}", day);
+        }
+    }
+}
@@ -0,0 +1,21 @@
+// Assemble a source file into the comma-separated format `parse` reads.
+//
+//     cargo run --bin assemble -- program.asm > input.txt
+
+use std::env;
+use std::fs;
+
+use intcode::{assemble, IntcodeError};
+
+fn main() -> Result<(), IntcodeError> {
+    let filename: String = env::args().nth(1).unwrap_or("./program.asm".to_string());
+    let source =
+        fs::read_to_string(&filename).map_err(|error| IntcodeError::reading(&filename, error))?;
+
+    let values: Vec<String> = assemble(&source)?
+        .iter()
+        .map(|value| value.to_string())
+        .collect();
+    println!("{}", values.join(","));
+    Ok(())
+}
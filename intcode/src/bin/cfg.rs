@@ -0,0 +1,25 @@
+// Control flow graph of an Intcode program in Graphviz DOT format.
+//
+//     cargo run --bin cfg -- ../day21_part1/input.txt | dot -Tsvg > day21.svg
+
+use std::env;
+use std::fs;
+
+use intcode::{analyse, parse_values, ControlFlowGraph, IntcodeError};
+
+fn main() -> Result<(), IntcodeError> {
+    let filename: String = env::args().nth(1).unwrap_or("./input.txt".to_string());
+    let data =
+        fs::read_to_string(&filename).map_err(|error| IntcodeError::reading(&filename, error))?;
+
+    let graph: ControlFlowGraph = analyse(&parse_values(&data)?);
+    print!("{}", graph.to_dot());
+    eprintln!(
+        "{} blocks, {} subroutines, {} indirect jumps, {} self-modifying writes",
+        graph.blocks.len(),
+        graph.subroutines.len(),
+        graph.indirect_jumps.len(),
+        graph.self_modifying_writes.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,18 @@
+// Interactive debugger for an Intcode program, `help` lists the commands.
+//
+//     cargo run --bin debug -- ../day13_part2/input.txt
+
+use std::env;
+use std::io;
+
+use intcode::{parse, Debugger, IntcodeComputer, IntcodeError};
+
+fn main() -> Result<(), IntcodeError> {
+    let filename: String = env::args().nth(1).unwrap_or("./input.txt".to_string());
+    let mut debugger = Debugger::new(IntcodeComputer::new(parse(&filename)?));
+
+    debugger
+        .repl(io::stdin().lock(), io::stdout())
+        .expect("Failed to use the terminal");
+    Ok(())
+}
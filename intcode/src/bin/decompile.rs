@@ -0,0 +1,17 @@
+// Decompile an Intcode program into structured pseudo-code.
+//
+//     cargo run --bin decompile -- ../day25_part1/input.txt > day25.txt
+
+use std::env;
+use std::fs;
+
+use intcode::{decompile, parse_values, IntcodeError};
+
+fn main() -> Result<(), IntcodeError> {
+    let filename: String = env::args().nth(1).unwrap_or("./input.txt".to_string());
+    let data =
+        fs::read_to_string(&filename).map_err(|error| IntcodeError::reading(&filename, error))?;
+
+    print!("{}", decompile(&parse_values(&data)?));
+    Ok(())
+}
@@ -0,0 +1,17 @@
+// Annotated listing of an Intcode program.
+//
+//     cargo run --bin disassemble -- ../day25_part1/input.txt
+
+use std::env;
+use std::fs;
+
+use intcode::{listing, parse_values, IntcodeError};
+
+fn main() -> Result<(), IntcodeError> {
+    let filename: String = env::args().nth(1).unwrap_or("./input.txt".to_string());
+    let data =
+        fs::read_to_string(&filename).map_err(|error| IntcodeError::reading(&filename, error))?;
+
+    print!("{}", listing(&parse_values(&data)?));
+    Ok(())
+}
@@ -0,0 +1,142 @@
+// Play an ASCII Intcode program from the terminal, with line editing and
+// history. Lines starting with `/` are for the player, `/help` lists them.
+//
+//     cargo run --features terminal --bin play -- ../day25_part1/input.txt
+//     cargo run --features terminal --bin play -- ../day25_part1/input.txt --replay moves.txt --save moves.txt
+//
+// `--replay` sends the commands of a saved transcript before handing over,
+// `--save` writes the transcript when the session ends.
+
+use std::env;
+use std::process;
+
+use intcode::{load_transcript, parse, IntcodeError, Session};
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+
+const PROMPT: &str = "> ";
+const HELP: &str = "/save <file>    write the commands sent so far to <file>
+/replay <file>  send the commands saved in <file>
+/history        show the commands sent so far
+/answers        show the values the program printed outside the ASCII range
+/quit           leave (so do ctrl-c and ctrl-d)";
+
+fn usage(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!("usage: play <program> [--replay transcript] [--save transcript]");
+    process::exit(2)
+}
+
+fn terminal_error(error: ReadlineError) -> IntcodeError {
+    IntcodeError::Device {
+        message: format!("terminal: {}", error),
+    }
+}
+
+// Print a reply, and any answers that came with it.
+fn report(session: &Session, text: &str, answers_before: usize) {
+    print!("{}", text);
+    for answer in &session.computer().answers()[answers_before..] {
+        println!("answer: {}", answer);
+    }
+}
+
+// Handle a `/` line. Returns false when the player wants to leave. Errors
+// are the player's to fix, so they are printed and the session carries on.
+fn meta_command(session: &mut Session, line: &str) -> Result<bool, IntcodeError> {
+    let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+    match (command, argument.trim()) {
+        ("/quit", _) => return Ok(false),
+        ("/save", filename) if !filename.is_empty() => {
+            session.save_transcript(filename)?;
+            println!(
+                "saved {} commands to {}",
+                session.transcript().len(),
+                filename
+            );
+        }
+        ("/replay", filename) if !filename.is_empty() => {
+            let commands: Vec<String> = load_transcript(filename)?;
+            let answers: usize = session.computer().answers().len();
+            let text: String = session.replay(&commands)?;
+            report(session, &text, answers);
+        }
+        ("/history", _) => {
+            for (index, command) in session.transcript().iter().enumerate() {
+                println!("{:>4}  {}", index + 1, command);
+            }
+        }
+        ("/answers", _) => {
+            for answer in session.computer().answers() {
+                println!("{}", answer);
+            }
+        }
+        _ => println!("{}", HELP),
+    }
+    Ok(true)
+}
+
+fn main() -> Result<(), IntcodeError> {
+    let arguments: Vec<String> = env::args().skip(1).collect();
+    let mut filename: String = "./input.txt".to_string();
+    let mut replay: Option<String> = None;
+    let mut save: Option<String> = None;
+
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        let mut value = || {
+            arguments
+                .next()
+                .cloned()
+                .unwrap_or_else(|| usage(&format!("missing value for {}", argument)))
+        };
+        match argument.as_str() {
+            "--replay" => replay = Some(value()),
+            "--save" => save = Some(value()),
+            _ => filename = argument.clone(),
+        }
+    }
+
+    let mut session = Session::new(parse(&filename)?);
+    print!("{}", session.start()?);
+    if let Some(replay) = replay {
+        let text: String = session.replay(&load_transcript(&replay)?)?;
+        report(&session, &text, 0);
+    }
+
+    let mut editor = DefaultEditor::new().map_err(terminal_error)?;
+    for command in session.transcript() {
+        let _ = editor.add_history_entry(command.as_str());
+    }
+    // the transcript is still saved when the terminal fails
+    let mut failure: Option<IntcodeError> = None;
+    while !session.is_halted() {
+        let line: String = match editor.readline(PROMPT) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
+            Err(error) => {
+                failure = Some(terminal_error(error));
+                break;
+            }
+        };
+        let _ = editor.add_history_entry(line.as_str());
+        if line.starts_with('/') {
+            match meta_command(&mut session, &line) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(error) => eprintln!("error: {}", error),
+            }
+            continue;
+        }
+        let answers: usize = session.computer().answers().len();
+        match session.command(&line) {
+            Ok(text) => report(&session, &text, answers),
+            Err(error) => eprintln!("error: {}", error),
+        }
+    }
+
+    if let Some(save) = save {
+        session.save_transcript(&save)?;
+    }
+    failure.map_or(Ok(()), Err)
+}
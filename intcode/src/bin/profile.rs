@@ -0,0 +1,30 @@
+// Profile an Intcode program and print where it spends its instructions.
+//
+//     cargo run --release --bin profile -- ../day09/input.txt 2
+//
+// Arguments after the program are its input values.
+
+use std::collections::VecDeque;
+use std::env;
+
+use intcode::{parse, parse_values, IntcodeComputer, IntcodeError, Profiler};
+
+fn main() -> Result<(), IntcodeError> {
+    let arguments: Vec<String> = env::args().skip(1).collect();
+    let filename: &str = arguments
+        .first()
+        .map_or("./input.txt", |name| name.as_str());
+    let mut input: VecDeque<i64> = if arguments.len() <= 1 {
+        VecDeque::new()
+    } else {
+        parse_values(&arguments[1..].join(","))?.into()
+    };
+
+    let mut computer = IntcodeComputer::new(parse(filename)?);
+    let mut profiler = Profiler::new();
+    let output: Vec<i64> = profiler.run(&mut computer, &mut input)?;
+
+    println!("output: {:?}\n", output);
+    println!("{}", profiler.report());
+    Ok(())
+}
@@ -0,0 +1,63 @@
+// Run an Intcode program and trace every instruction to stdout.
+//
+//     cargo run --bin trace -- ../day09/input.txt --input 1 --op out,hlt
+//     cargo run --bin trace -- ../day09/input.txt --input 2 --binary --from 900 --to 1000 > trace.bin
+//
+// Input is only what `--input` gives (comma separated), the run stops when
+// the program wants more.
+
+use std::collections::VecDeque;
+use std::env;
+use std::io;
+use std::process;
+
+use intcode::{
+    parse, parse_values, IntcodeComputer, IntcodeError, OperationType, TraceFilter, TraceFormat,
+    Tracer,
+};
+
+fn usage(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!(
+        "usage: trace <program> [--input 1,2] [--binary] [--from addr] [--to addr] [--op add,out]"
+    );
+    process::exit(2)
+}
+
+fn main() -> Result<(), IntcodeError> {
+    let arguments: Vec<String> = env::args().skip(1).collect();
+    let mut filename: String = "./input.txt".to_string();
+    let mut input: VecDeque<i64> = VecDeque::new();
+    let mut format: TraceFormat = TraceFormat::JsonLines;
+    let mut filter: TraceFilter = TraceFilter::default();
+    let (mut from, mut to): (i64, i64) = (0, i64::MAX);
+
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        let mut value = || {
+            arguments
+                .next()
+                .unwrap_or_else(|| usage(&format!("missing value for {}", argument)))
+        };
+        match argument.as_str() {
+            "--input" => input = parse_values(value())?.into(),
+            "--binary" => format = TraceFormat::Binary,
+            "--from" => from = parse_values(value())?[0],
+            "--to" => to = parse_values(value())?[0],
+            "--op" => {
+                for mnemonic in value().split(',') {
+                    let operation: OperationType = OperationType::from_mnemonic(mnemonic)
+                        .unwrap_or_else(|| usage(&format!("unknown opcode {}", mnemonic)));
+                    filter.operations.push(operation);
+                }
+            }
+            _ => filename = argument.clone(),
+        }
+    }
+    filter.addresses = Some(from..to);
+
+    let mut computer = IntcodeComputer::new(parse(&filename)?);
+    let mut tracer = Tracer::with_filter(io::stdout().lock(), format, filter);
+    tracer.run(&mut computer, &mut input)?;
+    Ok(())
+}
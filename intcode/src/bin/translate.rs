@@ -0,0 +1,18 @@
+// Translate an Intcode program to Rust source, see `intcode::translate`.
+//
+//     cargo run --bin translate -- ../day09/input.txt program > program.rs
+
+use std::env;
+use std::fs;
+
+use intcode::{parse_values, translate, IntcodeError};
+
+fn main() -> Result<(), IntcodeError> {
+    let filename: String = env::args().nth(1).unwrap_or("./input.txt".to_string());
+    let name: String = env::args().nth(2).unwrap_or("program".to_string());
+    let data =
+        fs::read_to_string(&filename).map_err(|error| IntcodeError::reading(&filename, error))?;
+
+    print!("{}", translate(&parse_values(&data)?, &name));
+    Ok(())
+}
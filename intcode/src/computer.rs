@@ -0,0 +1,680 @@
+use std::collections::VecDeque;
+use std::fs;
+
+use crate::device::{drive, InputDevice, OutputDevice};
+use crate::error::IntcodeError;
+use crate::memory::Memory;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OperationType {
+    SUM = 1,
+    MUL = 2,
+    CPY = 3,
+    OUT = 4,
+    JIT = 5,
+    JIF = 6,
+    LTH = 7,
+    EQL = 8,
+    ARB = 9,
+    END = 99,
+}
+
+impl OperationType {
+    pub fn from_i64(number: i64) -> Option<OperationType> {
+        match number {
+            1 => Some(OperationType::SUM),
+            2 => Some(OperationType::MUL),
+            3 => Some(OperationType::CPY),
+            4 => Some(OperationType::OUT),
+            5 => Some(OperationType::JIT),
+            6 => Some(OperationType::JIF),
+            7 => Some(OperationType::LTH),
+            8 => Some(OperationType::EQL),
+            9 => Some(OperationType::ARB),
+            99 => Some(OperationType::END),
+            _ => None,
+        }
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            OperationType::SUM => "add",
+            OperationType::MUL => "mul",
+            OperationType::CPY => "in",
+            OperationType::OUT => "out",
+            OperationType::JIT => "jnz",
+            OperationType::JIF => "jz",
+            OperationType::LTH => "lt",
+            OperationType::EQL => "eq",
+            OperationType::ARB => "rbo",
+            OperationType::END => "hlt",
+        }
+    }
+
+    pub fn from_mnemonic(mnemonic: &str) -> Option<OperationType> {
+        (1..=9)
+            .chain([99])
+            .filter_map(OperationType::from_i64)
+            .find(|operation| operation.mnemonic() == mnemonic)
+    }
+
+    // number of parameters following the instruction word
+    pub fn parameters(&self) -> usize {
+        match self {
+            OperationType::SUM | OperationType::MUL | OperationType::LTH | OperationType::EQL => 3,
+            OperationType::JIT | OperationType::JIF => 2,
+            OperationType::CPY | OperationType::OUT | OperationType::ARB => 1,
+            OperationType::END => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParameterMode {
+    PositionMode = 0,
+    ImmediateMode = 1,
+    RelativeMode = 2,
+}
+
+impl ParameterMode {
+    pub fn from_i64(number: i64) -> Option<ParameterMode> {
+        match number {
+            0 => Some(ParameterMode::PositionMode),
+            1 => Some(ParameterMode::ImmediateMode),
+            2 => Some(ParameterMode::RelativeMode),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Operation {
+    pub operation: OperationType,
+    pub first_parameter_mode: ParameterMode,
+    pub second_parameter_mode: ParameterMode,
+    pub third_parameter_mode: ParameterMode,
+}
+
+impl Operation {
+    // mode of parameter `parameter` (1-based)
+    pub fn parameter_mode(&self, parameter: usize) -> ParameterMode {
+        match parameter {
+            1 => self.first_parameter_mode,
+            2 => self.second_parameter_mode,
+            _ => self.third_parameter_mode,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepResult {
+    Executed,
+    NeedsInput,
+    Output(i64),
+    Halted,
+}
+
+// IntcodeComputer 'class'
+#[derive(Debug, Clone)]
+pub struct IntcodeComputer {
+    pub program: Memory,
+    pub pointer: i64,
+    pub relative_base: i64,
+    pub halted: bool,
+}
+
+impl IntcodeComputer {
+    pub fn new(program: Memory) -> Self {
+        IntcodeComputer {
+            program,
+            pointer: 0,
+            relative_base: 0,
+            halted: false,
+        }
+    }
+
+    // Independent copy of the machine to branch a search from.
+    pub fn fork(&self) -> Self {
+        self.clone()
+    }
+
+    // Execute a single instruction. A CPY with nothing in `input` is not
+    // executed: the pointer stays put and NeedsInput is returned instead.
+    pub fn step(&mut self, input: &mut VecDeque<i64>) -> Result<StepResult, IntcodeError> {
+        if self.halted {
+            return Ok(StepResult::Halted);
+        }
+        let operation: Operation = self.parse_instruction()?;
+
+        match operation.operation {
+            OperationType::SUM => self.sum(operation)?,
+            OperationType::MUL => self.mul(operation)?,
+            OperationType::CPY => match input.pop_front() {
+                Some(value) => self.cpy(value, operation)?,
+                None => return Ok(StepResult::NeedsInput),
+            },
+            OperationType::OUT => return Ok(StepResult::Output(self.out(operation)?)),
+            OperationType::JIT => self.jit(operation)?,
+            OperationType::JIF => self.jif(operation)?,
+            OperationType::LTH => self.lth(operation)?,
+            OperationType::EQL => self.eql(operation)?,
+            OperationType::ARB => self.arb(operation)?,
+            OperationType::END => {
+                self.halted = true;
+                return Ok(StepResult::Halted);
+            }
+        }
+        Ok(StepResult::Executed)
+    }
+
+    // Run until the next output value. None if the program halts or blocks
+    // on input first.
+    pub fn run_until_output(
+        &mut self,
+        input: &mut VecDeque<i64>,
+    ) -> Result<Option<i64>, IntcodeError> {
+        loop {
+            match self.step(input)? {
+                StepResult::Executed => continue,
+                StepResult::Output(value) => return Ok(Some(value)),
+                StepResult::NeedsInput | StepResult::Halted => return Ok(None),
+            }
+        }
+    }
+
+    // Run until the program halts, or until it asks for input and `input` is
+    // empty. Everything written by OUT in the meantime is returned.
+    pub fn run_until_input(&mut self, input: &mut VecDeque<i64>) -> Result<Vec<i64>, IntcodeError> {
+        let mut output: Vec<i64> = Vec::new();
+
+        loop {
+            match self.step(input)? {
+                StepResult::Executed => continue,
+                StepResult::Output(value) => output.push(value),
+                StepResult::NeedsInput | StepResult::Halted => return Ok(output),
+            }
+        }
+    }
+
+    pub fn run(&mut self, input: &mut VecDeque<i64>) -> Result<Vec<i64>, IntcodeError> {
+        self.run_until_input(input)
+    }
+
+    // Run with values read from `input` and written to `output` until the
+    // program halts or `input` has nothing more to give.
+    pub fn run_devices<I, O>(
+        &mut self,
+        input: &mut I,
+        output: &mut O,
+    ) -> Result<StepResult, IntcodeError>
+    where
+        I: InputDevice + ?Sized,
+        O: OutputDevice + ?Sized,
+    {
+        drive(|queue| self.step(queue), input, output)
+    }
+
+    pub fn parse_instruction(&self) -> Result<Operation, IntcodeError> {
+        parse_instruction(self.pointer, self.read(self.pointer)?)
+    }
+
+    // values the instruction at `pointer` reads, in parameter order
+    pub fn read_operands(&self) -> Result<Vec<i64>, IntcodeError> {
+        let operation: Operation = self.parse_instruction()?;
+        let reads: usize = match operation.operation {
+            OperationType::CPY | OperationType::END => 0,
+            OperationType::OUT | OperationType::ARB => 1,
+            _ => 2,
+        };
+        (1..=reads)
+            .map(|parameter| {
+                self.get_parameter(operation.parameter_mode(parameter), parameter as i64)
+            })
+            .collect()
+    }
+
+    // address the instruction at `pointer` is about to write, if it writes
+    pub fn write_address(&self) -> Result<Option<i64>, IntcodeError> {
+        let operation: Operation = self.parse_instruction()?;
+        match operation.operation {
+            OperationType::SUM | OperationType::MUL | OperationType::LTH | OperationType::EQL => {
+                Ok(Some(
+                    self.get_result_index(operation.third_parameter_mode, 3)?,
+                ))
+            }
+            OperationType::CPY => Ok(Some(
+                self.get_result_index(operation.first_parameter_mode, 1)?,
+            )),
+            _ => Ok(None),
+        }
+    }
+
+    fn read(&self, address: i64) -> Result<i64, IntcodeError> {
+        if address < 0 {
+            return Err(IntcodeError::NegativeAddress {
+                pointer: self.pointer,
+                address,
+            });
+        }
+        Ok(self.program.read(address as usize))
+    }
+
+    fn write(&mut self, address: i64, value: i64) -> Result<(), IntcodeError> {
+        if address < 0 {
+            return Err(IntcodeError::NegativeAddress {
+                pointer: self.pointer,
+                address,
+            });
+        }
+        self.program.write(address as usize, value);
+        Ok(())
+    }
+
+    fn get_parameter(
+        &self,
+        parameter_mode: ParameterMode,
+        offset: i64,
+    ) -> Result<i64, IntcodeError> {
+        let parameter: i64 = self.read(self.pointer.wrapping_add(offset))?;
+        match parameter_mode {
+            ParameterMode::PositionMode => self.read(parameter),
+            ParameterMode::ImmediateMode => Ok(parameter),
+            ParameterMode::RelativeMode => self.read(self.relative_base.wrapping_add(parameter)),
+        }
+    }
+
+    fn get_first_parameter(
+        &self,
+        first_parameter_mode: ParameterMode,
+    ) -> Result<i64, IntcodeError> {
+        self.get_parameter(first_parameter_mode, 1)
+    }
+
+    fn get_second_parameter(
+        &self,
+        second_parameter_mode: ParameterMode,
+    ) -> Result<i64, IntcodeError> {
+        self.get_parameter(second_parameter_mode, 2)
+    }
+
+    // Address a result is written to. Writes never use immediate mode.
+    fn get_result_index(
+        &self,
+        parameter_mode: ParameterMode,
+        offset: i64,
+    ) -> Result<i64, IntcodeError> {
+        let parameter: i64 = self.read(self.pointer.wrapping_add(offset))?;
+        match parameter_mode {
+            ParameterMode::PositionMode => Ok(parameter),
+            ParameterMode::RelativeMode => Ok(self.relative_base.wrapping_add(parameter)),
+            ParameterMode::ImmediateMode => Err(IntcodeError::ImmediateModeWrite {
+                pointer: self.pointer,
+                instruction: self.read(self.pointer)?,
+                parameter: offset as usize,
+            }),
+        }
+    }
+
+    // Arithmetic wraps on overflow, the same as in the JIT and in translated
+    // code. Addresses wrap too, so one past i64::MAX is a negative address.
+    fn sum(&mut self, operation: Operation) -> Result<(), IntcodeError> {
+        let parameter1: i64 = self.get_first_parameter(operation.first_parameter_mode)?;
+        let parameter2: i64 = self.get_second_parameter(operation.second_parameter_mode)?;
+        let result_index: i64 = self.get_result_index(operation.third_parameter_mode, 3)?;
+
+        self.write(result_index, parameter1.wrapping_add(parameter2))?;
+        self.pointer = self.pointer.wrapping_add(4);
+        Ok(())
+    }
+
+    fn mul(&mut self, operation: Operation) -> Result<(), IntcodeError> {
+        let parameter1: i64 = self.get_first_parameter(operation.first_parameter_mode)?;
+        let parameter2: i64 = self.get_second_parameter(operation.second_parameter_mode)?;
+        let result_index: i64 = self.get_result_index(operation.third_parameter_mode, 3)?;
+
+        self.write(result_index, parameter1.wrapping_mul(parameter2))?;
+        self.pointer = self.pointer.wrapping_add(4);
+        Ok(())
+    }
+
+    fn cpy(&mut self, input: i64, operation: Operation) -> Result<(), IntcodeError> {
+        let result_index: i64 = self.get_result_index(operation.first_parameter_mode, 1)?;
+
+        self.write(result_index, input)?;
+        self.pointer = self.pointer.wrapping_add(2);
+        Ok(())
+    }
+
+    fn out(&mut self, operation: Operation) -> Result<i64, IntcodeError> {
+        let operand: i64 = self.get_first_parameter(operation.first_parameter_mode)?;
+
+        self.pointer = self.pointer.wrapping_add(2);
+        Ok(operand)
+    }
+
+    fn jit(&mut self, operation: Operation) -> Result<(), IntcodeError> {
+        let parameter1: i64 = self.get_first_parameter(operation.first_parameter_mode)?;
+        let parameter2: i64 = self.get_second_parameter(operation.second_parameter_mode)?;
+
+        if parameter1 != 0 {
+            self.pointer = parameter2;
+        } else {
+            self.pointer = self.pointer.wrapping_add(3);
+        }
+        Ok(())
+    }
+
+    fn jif(&mut self, operation: Operation) -> Result<(), IntcodeError> {
+        let parameter1: i64 = self.get_first_parameter(operation.first_parameter_mode)?;
+        let parameter2: i64 = self.get_second_parameter(operation.second_parameter_mode)?;
+
+        if parameter1 == 0 {
+            self.pointer = parameter2;
+        } else {
+            self.pointer = self.pointer.wrapping_add(3);
+        }
+        Ok(())
+    }
+
+    fn lth(&mut self, operation: Operation) -> Result<(), IntcodeError> {
+        let parameter1: i64 = self.get_first_parameter(operation.first_parameter_mode)?;
+        let parameter2: i64 = self.get_second_parameter(operation.second_parameter_mode)?;
+        let result_index: i64 = self.get_result_index(operation.third_parameter_mode, 3)?;
+
+        self.write(result_index, if parameter1 < parameter2 { 1 } else { 0 })?;
+        self.pointer = self.pointer.wrapping_add(4);
+        Ok(())
+    }
+
+    fn eql(&mut self, operation: Operation) -> Result<(), IntcodeError> {
+        let parameter1: i64 = self.get_first_parameter(operation.first_parameter_mode)?;
+        let parameter2: i64 = self.get_second_parameter(operation.second_parameter_mode)?;
+        let result_index: i64 = self.get_result_index(operation.third_parameter_mode, 3)?;
+
+        self.write(result_index, if parameter1 == parameter2 { 1 } else { 0 })?;
+        self.pointer = self.pointer.wrapping_add(4);
+        Ok(())
+    }
+
+    fn arb(&mut self, operation: Operation) -> Result<(), IntcodeError> {
+        let parameter1: i64 = self.get_first_parameter(operation.first_parameter_mode)?;
+        self.relative_base = self.relative_base.wrapping_add(parameter1);
+
+        self.pointer = self.pointer.wrapping_add(2);
+        Ok(())
+    }
+}
+
+// Decode the instruction word found at `pointer`.
+pub fn parse_instruction(pointer: i64, instruction: i64) -> Result<Operation, IntcodeError> {
+    let operation: i64 = instruction % 100;
+    let parameters: i64 = instruction / 100;
+
+    let first_parameter_mode: i64 = parameters % 10;
+    let parameters: i64 = parameters / 10;
+    let second_parameter_mode: i64 = parameters % 10;
+    let parameters: i64 = parameters / 10;
+    let third_parameter_mode: i64 = parameters % 10;
+
+    let mode = |number: i64, parameter: usize| {
+        ParameterMode::from_i64(number).ok_or(IntcodeError::UnknownParameterMode {
+            pointer,
+            instruction,
+            parameter,
+        })
+    };
+
+    Ok(Operation {
+        operation: OperationType::from_i64(operation).ok_or(IntcodeError::UnknownOperation {
+            pointer,
+            instruction,
+        })?,
+        first_parameter_mode: mode(first_parameter_mode, 1)?,
+        second_parameter_mode: mode(second_parameter_mode, 2)?,
+        third_parameter_mode: mode(third_parameter_mode, 3)?,
+    })
+}
+
+// Convert comma-separated Intcode text into a list of integers.
+pub fn parse_values(data: &str) -> Result<Vec<i64>, IntcodeError> {
+    let mut values: Vec<i64> = Vec::new();
+
+    for (index, token) in data.split(',').enumerate() {
+        let value: i64 = token
+            .trim()
+            .parse::<i64>()
+            .map_err(|_| IntcodeError::InvalidToken {
+                position: index,
+                token: token.trim().to_string(),
+            })?;
+        values.push(value);
+    }
+
+    Ok(values)
+}
+
+// Convert comma-separated Intcode text into program memory.
+pub fn parse_program(data: &str) -> Result<Memory, IntcodeError> {
+    Ok(Memory::new(parse_values(data)?))
+}
+
+pub fn parse(filename: &str) -> Result<Memory, IntcodeError> {
+    // read file
+    let data =
+        fs::read_to_string(filename).map_err(|error| IntcodeError::reading(filename, error))?;
+
+    parse_program(&data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solve(vec_data: Vec<i64>, input: Vec<i64>) -> Vec<i64> {
+        let mut computer = IntcodeComputer::new(Memory::new(vec_data));
+        computer.run(&mut VecDeque::from(input)).unwrap()
+    }
+
+    #[test]
+    fn quine_outputs_itself() {
+        let quine: Vec<i64> = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        assert_eq!(solve(quine.clone(), vec![]), quine);
+    }
+
+    #[test]
+    fn large_numbers_are_supported() {
+        assert_eq!(
+            solve(vec![1102, 34915192, 34915192, 7, 4, 7, 99, 0], vec![]),
+            vec![1219070632396864]
+        );
+        assert_eq!(
+            solve(vec![104, 1125899906842624, 99], vec![]),
+            vec![1125899906842624]
+        );
+    }
+
+    #[test]
+    fn relative_mode_writes_use_relative_base() {
+        // ARB 10, CPY into [rb+0], OUT [rb+0], END
+        assert_eq!(solve(vec![109, 10, 203, 0, 204, 0, 99], vec![42]), vec![42]);
+        // ARB 10, SUM #2 #3 into [rb+1], OUT [rb+1], END
+        assert_eq!(
+            solve(vec![109, 10, 21101, 2, 3, 1, 204, 1, 99], vec![]),
+            vec![5]
+        );
+    }
+
+    #[test]
+    fn run_pauses_when_input_is_missing() {
+        let mut computer = IntcodeComputer::new(parse_program("3,9,4,9,3,9,4,9,99,0").unwrap());
+
+        let mut input: VecDeque<i64> = VecDeque::from([7]);
+        assert_eq!(computer.run(&mut input).unwrap(), vec![7]);
+        assert!(!computer.halted);
+
+        input.push_back(8);
+        assert_eq!(computer.run(&mut input).unwrap(), vec![8]);
+        assert!(computer.halted);
+    }
+
+    #[test]
+    fn step_reports_what_each_instruction_did() {
+        let mut computer = IntcodeComputer::new(parse_program("3,9,1001,9,1,9,4,9,99").unwrap());
+        let mut input: VecDeque<i64> = VecDeque::new();
+
+        assert_eq!(computer.step(&mut input).unwrap(), StepResult::NeedsInput);
+        assert_eq!(computer.pointer, 0);
+
+        input.push_back(41);
+        assert_eq!(computer.step(&mut input).unwrap(), StepResult::Executed);
+        assert_eq!(computer.step(&mut input).unwrap(), StepResult::Executed);
+        assert_eq!(computer.step(&mut input).unwrap(), StepResult::Output(42));
+        assert_eq!(computer.step(&mut input).unwrap(), StepResult::Halted);
+        assert!(computer.halted);
+        assert_eq!(computer.step(&mut input).unwrap(), StepResult::Halted);
+    }
+
+    #[test]
+    fn run_until_output_stops_after_each_value() {
+        let mut computer = IntcodeComputer::new(parse_program("104,1,104,2,99").unwrap());
+        let mut input: VecDeque<i64> = VecDeque::new();
+
+        assert_eq!(computer.run_until_output(&mut input).unwrap(), Some(1));
+        assert_eq!(computer.run_until_output(&mut input).unwrap(), Some(2));
+        assert_eq!(computer.run_until_output(&mut input).unwrap(), None);
+        assert!(computer.halted);
+    }
+
+    #[test]
+    fn errors_carry_pointer_instruction_and_parameter() {
+        let mut computer = IntcodeComputer::new(parse_program("1101,1,2,5,42").unwrap());
+        let mut input: VecDeque<i64> = VecDeque::new();
+
+        assert_eq!(computer.step(&mut input), Ok(StepResult::Executed));
+        assert_eq!(
+            computer.step(&mut input),
+            Err(IntcodeError::UnknownOperation {
+                pointer: 4,
+                instruction: 42
+            })
+        );
+
+        let mut computer = IntcodeComputer::new(parse_program("11101,1,2,5,99").unwrap());
+        assert_eq!(
+            computer.step(&mut input),
+            Err(IntcodeError::ImmediateModeWrite {
+                pointer: 0,
+                instruction: 11101,
+                parameter: 3
+            })
+        );
+
+        assert_eq!(
+            parse_instruction(7, 301),
+            Err(IntcodeError::UnknownParameterMode {
+                pointer: 7,
+                instruction: 301,
+                parameter: 1
+            })
+        );
+    }
+
+    #[test]
+    fn parse_program_reports_bad_tokens() {
+        assert_eq!(
+            parse_program("1,2,x3,99").err(),
+            Some(IntcodeError::InvalidToken {
+                position: 2,
+                token: "x3".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn negative_addresses_are_rejected() {
+        let mut computer = IntcodeComputer::new(parse_program("4,-3,99").unwrap());
+        let mut input: VecDeque<i64> = VecDeque::new();
+
+        assert_eq!(
+            computer.step(&mut input),
+            Err(IntcodeError::NegativeAddress {
+                pointer: 0,
+                address: -3
+            })
+        );
+    }
+
+    #[test]
+    fn arithmetic_wraps_on_overflow() {
+        // i64::MAX + 1 and i64::MAX * 2, printed
+        let program: String = format!(
+            "1101,{max},1,13,1102,{max},2,14,4,13,4,14,99,0,0",
+            max = i64::MAX
+        );
+        let mut computer = IntcodeComputer::new(parse_program(&program).unwrap());
+        assert_eq!(computer.run(&mut VecDeque::new()), Ok(vec![i64::MIN, -2]));
+    }
+
+    #[test]
+    fn relative_base_wraps_on_overflow() {
+        let program: String = format!("109,{},109,1,99", i64::MAX);
+        let mut computer = IntcodeComputer::new(parse_program(&program).unwrap());
+        assert_eq!(computer.run(&mut VecDeque::new()), Ok(vec![]));
+        assert_eq!(computer.relative_base, i64::MIN);
+    }
+
+    #[test]
+    fn relative_addresses_past_the_end_are_negative() {
+        // read, then write, one past i64::MAX
+        let program: String = format!("109,{},204,1,99", i64::MAX);
+        let mut computer = IntcodeComputer::new(parse_program(&program).unwrap());
+        assert_eq!(
+            computer.run(&mut VecDeque::new()),
+            Err(IntcodeError::NegativeAddress {
+                pointer: 2,
+                address: i64::MIN
+            })
+        );
+        let program: String = format!("109,{},203,1,99", i64::MAX);
+        let mut computer = IntcodeComputer::new(parse_program(&program).unwrap());
+        assert_eq!(
+            computer.run(&mut VecDeque::from([5])),
+            Err(IntcodeError::NegativeAddress {
+                pointer: 2,
+                address: i64::MIN
+            })
+        );
+    }
+
+    #[test]
+    fn parameters_past_the_end_are_negative() {
+        // puts an out instruction at i64::MAX and jumps to it
+        let program: String = format!("1101,104,0,{max},1105,1,{max}", max = i64::MAX);
+        let mut computer = IntcodeComputer::new(parse_program(&program).unwrap());
+        assert_eq!(
+            computer.run(&mut VecDeque::new()),
+            Err(IntcodeError::NegativeAddress {
+                pointer: i64::MAX,
+                address: i64::MIN
+            })
+        );
+    }
+
+    #[test]
+    fn file_errors_keep_their_kind() {
+        assert_eq!(
+            parse("./missing.txt").err(),
+            Some(IntcodeError::FileNotFound {
+                filename: "./missing.txt".to_string()
+            })
+        );
+        // a directory can be opened but not read
+        let directory: &str = env!("CARGO_MANIFEST_DIR");
+        assert!(matches!(
+            parse(directory),
+            Err(IntcodeError::Io { kind, .. }) if kind != std::io::ErrorKind::NotFound
+        ));
+    }
+}
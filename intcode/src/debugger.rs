@@ -0,0 +1,605 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt;
+use std::io::{BufRead, Write};
+
+use crate::computer::{IntcodeComputer, OperationType, StepResult};
+use crate::disassembler::{decode, Instruction};
+use crate::error::IntcodeError;
+use crate::history::{Change, History};
+use crate::snapshot::Snapshot;
+
+const HELP: &str = "\
+step [n]             execute n instructions (default 1)
+continue             run until a breakpoint, watchpoint, missing input or halt
+back [n]             undo n instructions (default 1)
+reverse-continue     run backwards until a breakpoint, watchpoint or the start
+break <addr|op>      break at an address or before every instruction of an opcode
+watch <addr>         break after an instruction writes to a memory cell
+delete <addr|op>     remove a breakpoint or watchpoint
+info                 list breakpoints and watchpoints
+regs                 show pointer, relative_base and state
+mem <addr> [count]   dump memory cells
+disas [addr] [count] disassemble instructions (default at pointer)
+poke <addr> <value>  write values to memory starting at addr, forgetting the undo history
+input <value>...     queue input values
+ascii <text>         queue text as ASCII, followed by a newline
+output               show every value output so far
+save <file>          write a snapshot of the machine, its pending input and output
+load <file>          restore a snapshot, forgetting the undo history
+quit                 leave the debugger";
+
+// Why `resume` handed control back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    Stepped,
+    Breakpoint(i64),
+    OpcodeBreakpoint(OperationType),
+    Watchpoint { address: i64, old: i64, new: i64 },
+    NeedsInput,
+    Halted,
+    // nothing left to undo
+    Start,
+}
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Stop::Stepped => write!(f, "stepped"),
+            Stop::Breakpoint(address) => write!(f, "breakpoint at {}", address),
+            Stop::OpcodeBreakpoint(operation) => {
+                write!(f, "breakpoint on {}", operation.mnemonic())
+            }
+            Stop::Watchpoint { address, old, new } => {
+                write!(f, "watchpoint [{}]: {} -> {}", address, old, new)
+            }
+            Stop::NeedsInput => write!(f, "waiting for input"),
+            Stop::Halted => write!(f, "halted"),
+            Stop::Start => write!(f, "start of history"),
+        }
+    }
+}
+
+// End of the cells `start` to `start + count`, if they can all be addressed.
+fn cells(start: i64, count: i64) -> Option<i64> {
+    if start < 0 || count < 0 {
+        return None;
+    }
+    start.checked_add(count)
+}
+
+fn invalid_range(start: i64, count: i64) -> String {
+    format!("invalid address range: {} cells from {}", count, start)
+}
+
+pub struct Debugger {
+    pub computer: IntcodeComputer,
+    pub input: VecDeque<i64>,
+    pub output: Vec<i64>,
+    pub history: History,
+    breakpoints: BTreeSet<i64>,
+    opcode_breakpoints: Vec<OperationType>,
+    watchpoints: BTreeSet<i64>,
+}
+
+impl Debugger {
+    pub fn new(computer: IntcodeComputer) -> Self {
+        Debugger {
+            computer,
+            input: VecDeque::new(),
+            output: Vec::new(),
+            history: History::new(),
+            breakpoints: BTreeSet::new(),
+            opcode_breakpoints: Vec::new(),
+            watchpoints: BTreeSet::new(),
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, address: i64) {
+        self.breakpoints.insert(address);
+    }
+
+    pub fn add_opcode_breakpoint(&mut self, operation: OperationType) {
+        if !self.opcode_breakpoints.contains(&operation) {
+            self.opcode_breakpoints.push(operation);
+        }
+    }
+
+    pub fn add_watchpoint(&mut self, address: i64) {
+        self.watchpoints.insert(address);
+    }
+
+    // Execute up to `steps` instructions, or until something stops execution
+    // when `steps` is None. Breakpoints are checked before every instruction
+    // but the first, so resuming from a breakpoint moves on.
+    pub fn resume(&mut self, steps: Option<usize>) -> Result<Stop, IntcodeError> {
+        let mut executed: usize = 0;
+        loop {
+            if steps == Some(executed) {
+                return Ok(Stop::Stepped);
+            }
+            if self.computer.halted {
+                return Ok(Stop::Halted);
+            }
+
+            let operation: OperationType = self.computer.parse_instruction()?.operation;
+            if executed > 0 && steps.is_none() {
+                if self.breakpoints.contains(&self.computer.pointer) {
+                    return Ok(Stop::Breakpoint(self.computer.pointer));
+                }
+                if self.opcode_breakpoints.contains(&operation) {
+                    return Ok(Stop::OpcodeBreakpoint(operation));
+                }
+            }
+
+            let watched: Option<(i64, i64)> = match self.computer.write_address()? {
+                Some(address) if self.watchpoints.contains(&address) => {
+                    Some((address, self.computer.program.read(address as usize)))
+                }
+                _ => None,
+            };
+
+            match self.history.step(&mut self.computer, &mut self.input)? {
+                StepResult::NeedsInput => return Ok(Stop::NeedsInput),
+                StepResult::Halted => return Ok(Stop::Halted),
+                StepResult::Output(value) => self.output.push(value),
+                StepResult::Executed => {}
+            }
+            executed += 1;
+
+            if let Some((address, old)) = watched {
+                let new: i64 = self.computer.program.read(address as usize);
+                return Ok(Stop::Watchpoint { address, old, new });
+            }
+        }
+    }
+
+    fn undo(&mut self) -> Option<Change> {
+        let change: Change = self
+            .history
+            .step_back(&mut self.computer, &mut self.input)?;
+        if change.output {
+            self.output.pop();
+        }
+        Some(change)
+    }
+
+    // Undo up to `steps` instructions.
+    pub fn step_back(&mut self, steps: usize) -> Stop {
+        for _ in 0..steps {
+            if self.undo().is_none() {
+                return Stop::Start;
+            }
+        }
+        Stop::Stepped
+    }
+
+    // Undo instructions until the pointer reaches a breakpoint, a write to a
+    // watched cell is undone or the history runs out. Watchpoints report the
+    // write as it happened going forwards.
+    pub fn reverse_continue(&mut self) -> Result<Stop, IntcodeError> {
+        loop {
+            let written: Option<(i64, i64)> = self.history.last().and_then(|change| change.write);
+            let new: Option<i64> =
+                written.map(|(address, _)| self.computer.program.read(address as usize));
+            if self.undo().is_none() {
+                return Ok(Stop::Start);
+            }
+
+            if let (Some((address, old)), Some(new)) = (written, new) {
+                if self.watchpoints.contains(&address) {
+                    return Ok(Stop::Watchpoint { address, old, new });
+                }
+            }
+            if self.breakpoints.contains(&self.computer.pointer) {
+                return Ok(Stop::Breakpoint(self.computer.pointer));
+            }
+            let operation: OperationType = self.computer.parse_instruction()?.operation;
+            if self.opcode_breakpoints.contains(&operation) {
+                return Ok(Stop::OpcodeBreakpoint(operation));
+            }
+        }
+    }
+
+    fn instruction_at(&self, address: i64) -> Option<Instruction> {
+        let values: Vec<i64> = (address..address.saturating_add(4))
+            .map(|cell| self.computer.program.read(cell as usize))
+            .collect();
+        decode(&values, 0).map(|mut instruction| {
+            instruction.address = address as usize;
+            instruction
+        })
+    }
+
+    fn registers(&self) -> String {
+        let current: String = match self.instruction_at(self.computer.pointer) {
+            Some(instruction) => instruction.to_string(),
+            None => format!(
+                ".data {}",
+                self.computer.program.read(self.computer.pointer as usize)
+            ),
+        };
+        format!(
+            "pointer {} relative_base {}{}\n{:>5}: {}",
+            self.computer.pointer,
+            self.computer.relative_base,
+            if self.computer.halted {
+                " (halted)"
+            } else {
+                ""
+            },
+            self.computer.pointer,
+            current
+        )
+    }
+
+    fn run(&mut self, steps: Option<usize>) -> Result<String, IntcodeError> {
+        let printed: usize = self.output.len();
+        let stop: Stop = self.resume(steps)?;
+        Ok(self.report(printed, stop))
+    }
+
+    fn report(&self, printed: usize, stop: Stop) -> String {
+        let mut text: String = String::new();
+        if self.output.len() > printed {
+            let output: Vec<String> = self.output[printed..]
+                .iter()
+                .map(|value| value.to_string())
+                .collect();
+            text.push_str(&format!("output: {}\n", output.join(", ")));
+        }
+        text.push_str(&format!("{}\n{}", stop, self.registers()));
+        text
+    }
+
+    fn dump(&self, start: i64, count: i64) -> String {
+        let end: i64 = match cells(start, count) {
+            Some(end) => end,
+            None => return invalid_range(start, count),
+        };
+        let mut lines: Vec<String> = Vec::new();
+        for row in (start..end).step_by(8) {
+            let values: Vec<String> = (row..row.saturating_add(8).min(end))
+                .map(|address| self.computer.program.read(address as usize).to_string())
+                .collect();
+            lines.push(format!("{:>5}: {}", row, values.join(" ")));
+        }
+        lines.join("\n")
+    }
+
+    fn disassemble(&self, start: i64, count: i64) -> String {
+        // an instruction takes up to four cells
+        if cells(start, count.saturating_mul(4)).is_none() {
+            return invalid_range(start, count);
+        }
+        let mut lines: Vec<String> = Vec::new();
+        let mut address: i64 = start;
+        for _ in 0..count {
+            match self.instruction_at(address) {
+                Some(instruction) => {
+                    lines.push(format!("{:>5}: {}", address, instruction));
+                    address += instruction.size() as i64;
+                }
+                None => {
+                    let value: i64 = self.computer.program.read(address as usize);
+                    lines.push(format!("{:>5}: .data {}", address, value));
+                    address += 1;
+                }
+            }
+        }
+        lines.join("\n")
+    }
+
+    // Run one debugger command and return what it prints. Malformed commands
+    // are reported in the returned text; VM errors are returned as errors.
+    pub fn execute(&mut self, command: &str) -> Result<String, IntcodeError> {
+        let words: Vec<&str> = command.split_whitespace().collect();
+        let numbers: Option<Vec<i64>> = words
+            .iter()
+            .skip(1)
+            .map(|word| word.parse::<i64>().ok())
+            .collect();
+        let numbers: Vec<i64> = match (words.first(), numbers) {
+            (Some(&"ascii" | &"save" | &"load"), _) => Vec::new(),
+            (Some(&"break" | &"b" | &"delete" | &"d"), _) => Vec::new(),
+            (_, Some(numbers)) => numbers,
+            (_, None) => return Ok(format!("invalid arguments: {}", command.trim())),
+        };
+
+        let text: String = match (words.first().copied(), numbers.as_slice()) {
+            (None, _) => String::new(),
+            (Some("step" | "s"), []) => self.run(Some(1))?,
+            (Some("step" | "s"), [steps]) => self.run(Some((*steps).max(0) as usize))?,
+            (Some("continue" | "c"), []) => self.run(None)?,
+            (Some("back"), []) => {
+                let stop: Stop = self.step_back(1);
+                self.report(self.output.len(), stop)
+            }
+            (Some("back"), [steps]) => {
+                let stop: Stop = self.step_back((*steps).max(0) as usize);
+                self.report(self.output.len(), stop)
+            }
+            (Some("reverse-continue" | "rc"), []) => {
+                let stop: Stop = self.reverse_continue()?;
+                self.report(self.output.len(), stop)
+            }
+            (Some("break" | "b"), _) if words.len() == 2 => match words[1].parse::<i64>() {
+                Ok(address) => {
+                    self.add_breakpoint(address);
+                    format!("breakpoint at {}", address)
+                }
+                Err(_) => match OperationType::from_mnemonic(words[1]) {
+                    Some(operation) => {
+                        self.add_opcode_breakpoint(operation);
+                        format!("breakpoint on {}", operation.mnemonic())
+                    }
+                    None => format!("unknown address or opcode {:?}", words[1]),
+                },
+            },
+            (Some("watch" | "w"), [address]) if *address < 0 => {
+                format!("invalid address {}", address)
+            }
+            (Some("watch" | "w"), [address]) => {
+                self.add_watchpoint(*address);
+                format!("watchpoint on [{}]", address)
+            }
+            (Some("delete" | "d"), _) if words.len() == 2 => {
+                let removed: bool = match words[1].parse::<i64>() {
+                    Ok(address) => {
+                        self.breakpoints.remove(&address) | self.watchpoints.remove(&address)
+                    }
+                    Err(_) => {
+                        let count: usize = self.opcode_breakpoints.len();
+                        self.opcode_breakpoints
+                            .retain(|operation| operation.mnemonic() != words[1]);
+                        count != self.opcode_breakpoints.len()
+                    }
+                };
+                if removed {
+                    format!("deleted {}", words[1])
+                } else {
+                    format!("nothing set on {}", words[1])
+                }
+            }
+            (Some("info" | "i"), []) => {
+                let opcodes: Vec<&str> = self
+                    .opcode_breakpoints
+                    .iter()
+                    .map(|operation| operation.mnemonic())
+                    .collect();
+                format!(
+                    "breakpoints: {:?}\nopcode breakpoints: {:?}\nwatchpoints: {:?}",
+                    self.breakpoints, opcodes, self.watchpoints
+                )
+            }
+            (Some("regs" | "r"), []) => self.registers(),
+            (Some("mem" | "x"), [start]) => self.dump(*start, 8),
+            (Some("mem" | "x"), [start, count]) => self.dump(*start, *count),
+            (Some("disas"), []) => self.disassemble(self.computer.pointer, 8),
+            (Some("disas"), [start]) => self.disassemble(*start, 8),
+            (Some("disas"), [start, count]) => self.disassemble(*start, *count),
+            (Some("poke" | "p"), [address, values @ ..]) if !values.is_empty() => {
+                if cells(*address, values.len() as i64).is_none() {
+                    return Ok(invalid_range(*address, values.len() as i64));
+                }
+                for (offset, value) in values.iter().enumerate() {
+                    self.computer
+                        .program
+                        .write(*address as usize + offset, *value);
+                }
+                // the undo log cannot put the old values back
+                self.history.clear();
+                self.dump(*address, values.len() as i64)
+            }
+            (Some("input"), values) if !values.is_empty() => {
+                self.input.extend(values);
+                format!("{} values queued", self.input.len())
+            }
+            (Some("ascii"), _) => {
+                let text: &str = command.trim_start()[5..].trim();
+                self.input.extend(text.chars().map(|c| c as i64));
+                self.input.push_back(10);
+                format!("{} values queued", self.input.len())
+            }
+            (Some("save"), _) if words.len() == 2 => {
+                let snapshot = Snapshot {
+                    computer: self.computer.fork(),
+                    input: self.input.clone(),
+                    output: self.output.clone(),
+                };
+                snapshot.save(words[1])?;
+                format!("saved {}", words[1])
+            }
+            (Some("load"), _) if words.len() == 2 => {
+                let snapshot: Snapshot = Snapshot::load(words[1])?;
+                self.computer = snapshot.computer;
+                self.input = snapshot.input;
+                self.output = snapshot.output;
+                self.history.clear();
+                self.registers()
+            }
+            (Some("output" | "o"), []) => {
+                let output: Vec<String> =
+                    self.output.iter().map(|value| value.to_string()).collect();
+                output.join(", ")
+            }
+            (Some("help" | "h"), []) => HELP.to_string(),
+            _ => format!("unknown command: {} (try help)", command.trim()),
+        };
+        Ok(text)
+    }
+
+    // Read commands until `quit` or end of input. VM errors are printed and
+    // leave the debugger open so the state can still be inspected.
+    pub fn repl<R: BufRead, W: Write>(&mut self, reader: R, mut writer: W) -> std::io::Result<()> {
+        writeln!(writer, "{}", self.registers())?;
+        write!(writer, "(debug) ")?;
+        writer.flush()?;
+        for line in reader.lines() {
+            let line: String = line?;
+            if matches!(line.trim(), "quit" | "q") {
+                break;
+            }
+            match self.execute(&line) {
+                Ok(text) if text.is_empty() => {}
+                Ok(text) => writeln!(writer, "{}", text)?,
+                Err(error) => writeln!(writer, "error: {}", error)?,
+            }
+            write!(writer, "(debug) ")?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assemble, Memory};
+
+    fn debugger(source: &str) -> Debugger {
+        Debugger::new(IntcodeComputer::new(Memory::new(assemble(source).unwrap())))
+    }
+
+    // count down from the input value, printing each number
+    const COUNTDOWN: &str = "
+                in [20]
+        loop:   out [20]
+                add [20], #-1, [20]
+                jnz [20], #loop
+                hlt
+    ";
+
+    #[test]
+    fn stops_at_breakpoints_and_reports_output() {
+        let mut debugger = debugger(COUNTDOWN);
+        debugger.input.push_back(3);
+        debugger.add_breakpoint(4);
+
+        assert_eq!(debugger.resume(None), Ok(Stop::Breakpoint(4)));
+        assert_eq!(debugger.output, vec![3]);
+        assert_eq!(debugger.resume(None), Ok(Stop::Breakpoint(4)));
+        assert_eq!(debugger.resume(None), Ok(Stop::Breakpoint(4)));
+        assert_eq!(debugger.resume(None), Ok(Stop::Halted));
+        assert_eq!(debugger.output, vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn stops_on_opcodes_watchpoints_and_missing_input() {
+        let mut debugger = debugger(COUNTDOWN);
+        assert_eq!(debugger.resume(None), Ok(Stop::NeedsInput));
+
+        debugger.input.push_back(2);
+        debugger.add_watchpoint(20);
+        assert_eq!(
+            debugger.resume(None),
+            Ok(Stop::Watchpoint {
+                address: 20,
+                old: 0,
+                new: 2
+            })
+        );
+
+        assert_eq!(
+            debugger.resume(None),
+            Ok(Stop::Watchpoint {
+                address: 20,
+                old: 2,
+                new: 1
+            })
+        );
+        assert_eq!(debugger.execute("delete 20").unwrap(), "deleted 20");
+        debugger.add_opcode_breakpoint(OperationType::JIT);
+        assert_eq!(
+            debugger.resume(None),
+            Ok(Stop::OpcodeBreakpoint(OperationType::JIT))
+        );
+        // the jnz the watchpoint stopped in front of is not reported
+        assert_eq!(debugger.output, vec![2, 1]);
+        assert_eq!(debugger.resume(Some(1)), Ok(Stop::Stepped));
+        assert_eq!(debugger.resume(Some(1)), Ok(Stop::Halted));
+    }
+
+    #[test]
+    fn runs_backwards_to_breakpoints_and_watchpoints() {
+        let mut debugger = debugger(COUNTDOWN);
+        debugger.input.push_back(3);
+        assert_eq!(debugger.resume(None), Ok(Stop::Halted));
+
+        debugger.add_breakpoint(2);
+        assert_eq!(debugger.reverse_continue(), Ok(Stop::Breakpoint(2)));
+        assert_eq!(debugger.output, vec![3, 2]);
+        assert_eq!(debugger.computer.program.read(20), 1);
+
+        debugger.add_watchpoint(20);
+        assert_eq!(
+            debugger.reverse_continue(),
+            Ok(Stop::Watchpoint {
+                address: 20,
+                old: 2,
+                new: 1
+            })
+        );
+        assert_eq!(debugger.computer.pointer, 4);
+        assert_eq!(debugger.step_back(100), Stop::Start);
+        assert_eq!(debugger.computer.pointer, 0);
+        assert_eq!(debugger.input, VecDeque::from([3]));
+        assert!(debugger.output.is_empty());
+    }
+
+    #[test]
+    fn commands_inspect_and_patch_state() {
+        let mut debugger = debugger(COUNTDOWN);
+        assert_eq!(debugger.execute("poke 20 7 8").unwrap(), "   20: 7 8");
+        assert_eq!(debugger.execute("mem 19 3").unwrap(), "   19: 0 7 8");
+        assert_eq!(
+            debugger.execute("disas 2 2").unwrap(),
+            "    2: out [20]\n    4: add [20], #-1, [20]"
+        );
+        assert_eq!(debugger.execute("input 5").unwrap(), "1 values queued");
+        assert_eq!(
+            debugger.execute("step 2").unwrap(),
+            "output: 5\nstepped\npointer 4 relative_base 0\n    4: add [20], #-1, [20]"
+        );
+        for command in ["mem -1", "mem 0 -2", "disas -4", "poke -1 5", "watch -3"] {
+            assert!(debugger
+                .execute(command)
+                .unwrap()
+                .starts_with("invalid address"));
+        }
+        let overflow: String = format!("mem {} 10", i64::MAX - 2);
+        assert!(debugger
+            .execute(&overflow)
+            .unwrap()
+            .starts_with("invalid address"));
+
+        // pokes cannot be undone, so they drop the history
+        assert!(!debugger.history.is_empty());
+        debugger.execute("poke 21 1").unwrap();
+        assert!(debugger.history.is_empty());
+        assert!(debugger
+            .execute("frobnicate")
+            .unwrap()
+            .starts_with("unknown command"));
+    }
+
+    #[test]
+    fn snapshots_keep_the_output() {
+        let mut debugger = debugger(COUNTDOWN);
+        debugger.input.push_back(3);
+        debugger.add_breakpoint(4);
+        assert_eq!(debugger.resume(None), Ok(Stop::Breakpoint(4)));
+        assert_eq!(debugger.resume(None), Ok(Stop::Breakpoint(4)));
+
+        let filename =
+            std::env::temp_dir().join(format!("intcode-{}.debugger", std::process::id()));
+        let filename: &str = filename.to_str().unwrap();
+        debugger.execute(&format!("save {}", filename)).unwrap();
+        let mut restored = Debugger::new(IntcodeComputer::new(Memory::new(Vec::new())));
+        restored.execute(&format!("load {}", filename)).unwrap();
+        std::fs::remove_file(filename).unwrap();
+
+        assert_eq!(restored.output, vec![3, 2]);
+        assert_eq!(restored.resume(None), Ok(Stop::Halted));
+        assert_eq!(restored.output, vec![3, 2, 1]);
+    }
+}
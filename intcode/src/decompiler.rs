@@ -0,0 +1,617 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::analysis::{analyse, Block, ControlFlowGraph, EdgeKind};
+use crate::computer::{OperationType, ParameterMode};
+use crate::disassembler::{moved_constant, Instruction};
+
+// Names used in the pseudo-code:
+//
+//     g1128       global cell at address 1128
+//     patch_1447  code cell that the program rewrites before running it
+//     v1, v2      cells of the current stack frame, [rb-frame+1], ...
+//     out1, out2  cells just past the frame, where calls take their
+//                 arguments and leave their results
+//     sub_1234    subroutine entered at address 1234
+//     L_57        address 57, target of a goto
+
+#[derive(Debug, Clone)]
+struct Statement {
+    // variable assigned, if any
+    target: Option<String>,
+    text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LoopKind {
+    // ends with a conditional jump back to the header
+    DoWhile,
+    // ends with an unconditional jump back to the header
+    Forever,
+}
+
+// Where control goes after the range being emitted, for break/continue.
+#[derive(Debug, Clone, Copy)]
+struct Context {
+    // block reached when the range ends
+    follow: Option<usize>,
+    // header and exit block of the innermost loop
+    innermost: Option<(usize, Option<usize>)>,
+}
+
+struct Function<'a> {
+    graph: &'a ControlFlowGraph,
+    patched: &'a BTreeSet<usize>,
+    entries: &'a BTreeSet<usize>,
+    frame: i64,
+    // block starts in address order
+    blocks: Vec<usize>,
+    loops: BTreeMap<usize, (usize, LoopKind)>,
+    labels: BTreeSet<usize>,
+    globals: BTreeSet<i64>,
+    lines: Vec<String>,
+}
+
+fn simplify_sum(a: String, b: String) -> String {
+    match (a.as_str(), b.as_str()) {
+        (_, "0") => a,
+        ("0", _) => b,
+        (_, negative) if negative.starts_with('-') => format!("{} - {}", a, &negative[1..]),
+        _ => format!("{} + {}", a, b),
+    }
+}
+
+fn simplify_product(a: String, b: String) -> String {
+    match (a.as_str(), b.as_str()) {
+        (_, "1") => a,
+        ("1", _) => b,
+        (_, "-1") => format!("-{}", a),
+        ("-1", _) => format!("-{}", b),
+        _ => format!("{} * {}", a, b),
+    }
+}
+
+fn negate(condition: &str) -> String {
+    match condition.strip_prefix('!') {
+        Some(inner) => inner.to_string(),
+        None => format!("!{}", condition),
+    }
+}
+
+impl<'a> Function<'a> {
+    fn operand(&mut self, instruction: &Instruction, parameter: usize) -> String {
+        let value: i64 = instruction.parameters[parameter - 1];
+        if self.patched.contains(&(instruction.address + parameter)) {
+            let cell: String = format!("patch_{}", instruction.address + parameter);
+            return match instruction.operation.parameter_mode(parameter) {
+                ParameterMode::PositionMode => format!("mem[{}]", cell),
+                ParameterMode::ImmediateMode => cell,
+                ParameterMode::RelativeMode => format!("mem[rb + {}]", cell),
+            };
+        }
+        match instruction.operation.parameter_mode(parameter) {
+            ParameterMode::ImmediateMode
+                if moved_constant(instruction) == Some(value)
+                    && value >= 0
+                    && self.entries.contains(&(value as usize)) =>
+            {
+                format!("&sub_{}", value)
+            }
+            ParameterMode::ImmediateMode => value.to_string(),
+            ParameterMode::PositionMode if self.patched.contains(&(value as usize)) => {
+                format!("patch_{}", value)
+            }
+            ParameterMode::PositionMode => {
+                self.globals.insert(value);
+                format!("g{}", value)
+            }
+            ParameterMode::RelativeMode if value < 0 => match self.frame + value {
+                0 => "ret".to_string(),
+                slot if slot > 0 => format!("v{}", slot),
+                _ => format!("mem[rb - {}]", -value),
+            },
+            ParameterMode::RelativeMode => format!("out{}", value),
+        }
+    }
+
+    fn statement(&mut self, instruction: &Instruction) -> Option<Statement> {
+        let assign = |target: String, text: String| Statement {
+            text: format!("{} = {};", target, text),
+            target: Some(target),
+        };
+        let operation: OperationType = instruction.operation.operation;
+        match operation {
+            OperationType::SUM | OperationType::MUL | OperationType::LTH | OperationType::EQL => {
+                let a: String = self.operand(instruction, 1);
+                let b: String = self.operand(instruction, 2);
+                let target: String = self.operand(instruction, 3);
+                let text: String = match operation {
+                    OperationType::SUM => simplify_sum(a, b),
+                    OperationType::MUL => simplify_product(a, b),
+                    OperationType::LTH => format!("{} < {}", a, b),
+                    _ => format!("{} == {}", a, b),
+                };
+                Some(assign(target, text))
+            }
+            OperationType::CPY => {
+                let target: String = self.operand(instruction, 1);
+                Some(assign(target, "input()".to_string()))
+            }
+            OperationType::OUT => Some(Statement {
+                target: None,
+                text: format!("output({});", self.operand(instruction, 1)),
+            }),
+            OperationType::ARB => Some(Statement {
+                target: None,
+                text: format!("rb += {};", self.operand(instruction, 1)),
+            }),
+            OperationType::END => Some(Statement {
+                target: None,
+                text: "halt();".to_string(),
+            }),
+            OperationType::JIT | OperationType::JIF => None,
+        }
+    }
+
+    // condition under which the jump ending `instruction` is taken
+    fn condition(&mut self, instruction: &Instruction) -> String {
+        let value: String = self.operand(instruction, 1);
+        if instruction.operation.operation == OperationType::JIT {
+            value
+        } else {
+            negate(&value)
+        }
+    }
+
+    fn emit(&mut self, depth: usize, text: String) {
+        self.lines.push(format!("{}{}", "    ".repeat(depth), text));
+    }
+
+    fn position(&self, start: usize) -> Option<usize> {
+        self.blocks.binary_search(&start).ok()
+    }
+
+    fn is_return(&self, block: &Block) -> bool {
+        let last: &Instruction = block.last();
+        last.ends_flow()
+            && last.jump_target().is_none()
+            && last.operation.second_parameter_mode == ParameterMode::RelativeMode
+            && last.parameters[1] == 0
+    }
+
+    fn jump(&mut self, target: usize, depth: usize, context: Context) {
+        if Some(target) == context.follow {
+            return;
+        }
+        match context.innermost {
+            Some((header, _)) if header == target => self.emit(depth, "continue;".to_string()),
+            Some((_, Some(exit))) if exit == target => self.emit(depth, "break;".to_string()),
+            _ => {
+                self.labels.insert(target);
+                self.emit(depth, format!("goto L_{};", target));
+            }
+        }
+    }
+
+    fn block_body(&mut self, block: &Block) -> Vec<Statement> {
+        let graph: &ControlFlowGraph = self.graph;
+        let mut instructions: &[Instruction] = &block.instructions;
+        let frame: i64 = self.frame;
+        let is_frame_change = |instruction: &Instruction, size: i64| {
+            instruction.operation.operation == OperationType::ARB
+                && instruction.operation.first_parameter_mode == ParameterMode::ImmediateMode
+                && instruction.parameters[0] == size
+        };
+
+        // prologue and epilogue of the frame
+        if frame > 0 && graph.subroutines.contains_key(&block.start) {
+            instructions = &instructions[1..];
+        }
+        if frame > 0 && self.is_return(block) {
+            if let [body @ .., epilogue, _] = instructions {
+                if is_frame_change(epilogue, -frame) {
+                    instructions = body;
+                }
+            }
+        }
+
+        let returns_to: Option<usize> = block
+            .successors
+            .iter()
+            .find(|edge| edge.kind == EdgeKind::Return)
+            .map(|edge| edge.to);
+        instructions
+            .iter()
+            .filter(|instruction| {
+                // the return address pushed before a call
+                returns_to.is_none()
+                    || moved_constant(instruction) != returns_to.map(|to| to as i64)
+            })
+            .filter_map(|instruction| self.statement(instruction))
+            .collect()
+    }
+
+    fn call(&mut self, mut statements: Vec<Statement>, callee: String, depth: usize) {
+        // fold the arguments stored just before the call into it
+        let mut arguments: BTreeMap<i64, String> = BTreeMap::new();
+        while let Some(statement) = statements.last() {
+            let slot: Option<i64> = statement
+                .target
+                .as_ref()
+                .and_then(|target| target.strip_prefix("out"))
+                .and_then(|slot| slot.parse().ok())
+                .filter(|slot| *slot > 0 && !arguments.contains_key(slot));
+            match slot {
+                Some(slot) => {
+                    let value: &str = statement.text.split_once(" = ").unwrap().1;
+                    arguments.insert(slot, value.trim_end_matches(';').to_string());
+                    statements.pop();
+                }
+                None => break,
+            }
+        }
+        let contiguous: bool = arguments.keys().copied().eq(1..=arguments.len() as i64);
+        if !contiguous {
+            for (slot, value) in &arguments {
+                statements.push(Statement {
+                    target: Some(format!("out{}", slot)),
+                    text: format!("out{} = {};", slot, value),
+                });
+            }
+            arguments.clear();
+        }
+        for statement in statements {
+            self.emit(depth, statement.text);
+        }
+        let arguments: Vec<String> = arguments.into_values().collect();
+        self.emit(depth, format!("{}({});", callee, arguments.join(", ")));
+    }
+
+    // Emit blocks from position `from` up to, not including, `to`.
+    fn range(
+        &mut self,
+        mut from: usize,
+        to: usize,
+        depth: usize,
+        context: Context,
+        open: Option<usize>,
+    ) {
+        while from < to {
+            let start: usize = self.blocks[from];
+            if self.labels.contains(&start) {
+                self.lines.push(format!("L_{}:", start));
+            }
+
+            if let Some((end, kind)) = self.loops.get(&from).copied() {
+                if end < to && open != Some(from) {
+                    let exit: Option<usize> = self.blocks.get(end + 1).copied();
+                    let inner = Context {
+                        follow: Some(start),
+                        innermost: Some((start, exit)),
+                    };
+                    match kind {
+                        LoopKind::DoWhile => {
+                            self.emit(depth, "do {".to_string());
+                            self.range(from, end + 1, depth + 1, inner, Some(from));
+                            let last: Instruction =
+                                self.graph.blocks[&self.blocks[end]].last().clone();
+                            let condition: String = self.condition(&last);
+                            self.emit(depth, format!("}} while ({});", condition));
+                        }
+                        LoopKind::Forever => {
+                            self.emit(depth, "loop {".to_string());
+                            self.range(from, end + 1, depth + 1, inner, Some(from));
+                            self.emit(depth, "}".to_string());
+                        }
+                    }
+                    from = end + 1;
+                    continue;
+                }
+            }
+
+            let block: &Block = &self.graph.blocks[&start];
+            let statements: Vec<Statement> = self.block_body(block);
+            let last: Instruction = block.last().clone();
+            let next: Option<usize> = self.blocks.get(from + 1).copied();
+            let local_context = Context {
+                follow: if from + 1 == to { context.follow } else { next },
+                innermost: context.innermost,
+            };
+
+            let returns_to: Option<usize> = block
+                .successors
+                .iter()
+                .find(|edge| edge.kind == EdgeKind::Return)
+                .map(|edge| edge.to);
+            let call: Option<String> = match block
+                .successors
+                .iter()
+                .find(|edge| edge.kind == EdgeKind::Call)
+            {
+                Some(edge) => Some(format!("sub_{}", edge.to)),
+                // through a function pointer
+                None if returns_to.is_some() => Some(format!("(*{})", self.operand(&last, 2))),
+                None => None,
+            };
+            if let Some(callee) = call {
+                self.call(statements, callee, depth);
+                match returns_to {
+                    Some(target) => self.jump(target, depth, local_context),
+                    None => self.emit(depth, "halt(); // no return address pushed".to_string()),
+                }
+                from += 1;
+                continue;
+            }
+            for statement in statements {
+                self.emit(depth, statement.text);
+            }
+
+            let taken: Option<usize> = block
+                .successors
+                .iter()
+                .find(|edge| matches!(edge.kind, EdgeKind::Branch | EdgeKind::Jump))
+                .map(|edge| edge.to);
+            match (last.operation.operation, taken) {
+                (OperationType::END, _) => {}
+                (OperationType::JIT | OperationType::JIF, None) if self.is_return(block) => {
+                    self.emit(depth, "return;".to_string())
+                }
+                (OperationType::JIT | OperationType::JIF, None) => {
+                    let target: String = self.operand(&last, 2);
+                    let condition: String = self.condition(&last);
+                    if last.ends_flow() {
+                        self.emit(depth, format!("goto *{};", target));
+                    } else {
+                        self.emit(depth, format!("if ({}) goto *{};", condition, target));
+                    }
+                }
+                (_, Some(target)) if last.ends_flow() => self.jump(target, depth, local_context),
+                (_, Some(target)) => {
+                    let condition: String = self.condition(&last);
+                    let loop_end: bool = open.is_some_and(|header| {
+                        self.blocks[header] == target && self.loops[&header].0 == from
+                    });
+                    match self.position(target) {
+                        _ if loop_end => {}
+                        Some(position)
+                            if position > from + 1
+                                && (position < to
+                                    || self.blocks.get(position).copied() == context.follow) =>
+                        {
+                            // if-then, or if-else when the then part jumps over the rest
+                            let before: &Block = &self.graph.blocks[&self.blocks[position - 1]];
+                            let over: Option<usize> = before
+                                .successors
+                                .iter()
+                                .find(|edge| edge.kind == EdgeKind::Jump)
+                                .and_then(|edge| self.position(edge.to))
+                                .filter(|over| *over > position && *over <= to);
+                            let join: usize = over.unwrap_or(position);
+                            let inner = Context {
+                                follow: self.blocks.get(join).copied(),
+                                innermost: context.innermost,
+                            };
+                            self.emit(depth, format!("if ({}) {{", negate(&condition)));
+                            self.range(from + 1, position, depth + 1, inner, None);
+                            if join > position {
+                                self.emit(depth, "} else {".to_string());
+                                self.range(position, join, depth + 1, inner, None);
+                            }
+                            self.emit(depth, "}".to_string());
+                            from = join;
+                            continue;
+                        }
+                        _ => {
+                            let mut jump: Vec<String> = Vec::new();
+                            std::mem::swap(&mut jump, &mut self.lines);
+                            self.jump(target, 0, context);
+                            std::mem::swap(&mut jump, &mut self.lines);
+                            if let Some(statement) = jump.pop() {
+                                self.emit(depth, format!("if ({}) {}", condition, statement));
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+            from += 1;
+        }
+    }
+
+    fn render(&mut self) {
+        let context = Context {
+            follow: None,
+            innermost: None,
+        };
+        self.range(0, self.blocks.len(), 1, context, None);
+    }
+}
+
+// Structured pseudo-code for an Intcode image: one function for the code
+// reached from address 0 and one for every subroutine found by `analyse`.
+pub fn decompile(values: &[i64]) -> String {
+    let graph: ControlFlowGraph = analyse(values);
+    let patched: BTreeSet<usize> = graph
+        .self_modifying_writes
+        .iter()
+        .map(|write| write.target)
+        .collect();
+    let entries: BTreeSet<usize> = graph.subroutines.keys().copied().collect();
+
+    let mut functions: Vec<(String, i64, BTreeSet<usize>)> =
+        vec![("main".to_string(), 0, graph.region(0))];
+    for subroutine in graph.subroutines.values() {
+        functions.push((
+            format!("sub_{}", subroutine.entry),
+            subroutine.frame,
+            subroutine.blocks.clone(),
+        ));
+    }
+
+    let mut globals: BTreeSet<i64> = BTreeSet::new();
+    let mut bodies: Vec<String> = Vec::new();
+    for (name, frame, blocks) in functions {
+        let blocks: Vec<usize> = blocks.into_iter().collect();
+        let mut loops: BTreeMap<usize, (usize, LoopKind)> = BTreeMap::new();
+        for (position, start) in blocks.iter().enumerate() {
+            for edge in &graph.blocks[start].successors {
+                let header: Option<usize> = blocks.binary_search(&edge.to).ok();
+                let kind: LoopKind = match edge.kind {
+                    EdgeKind::Branch => LoopKind::DoWhile,
+                    EdgeKind::Jump => LoopKind::Forever,
+                    _ => continue,
+                };
+                if let Some(header) = header.filter(|header| *header <= position) {
+                    let end = loops.entry(header).or_insert((position, kind));
+                    if position > end.0 {
+                        *end = (position, kind);
+                    }
+                }
+            }
+        }
+
+        let mut function = Function {
+            graph: &graph,
+            patched: &patched,
+            entries: &entries,
+            frame,
+            blocks,
+            loops,
+            labels: BTreeSet::new(),
+            globals: BTreeSet::new(),
+            lines: Vec::new(),
+        };
+        // the first pass finds the goto targets that need a label
+        function.render();
+        function.lines.clear();
+        function.render();
+
+        let mut parameters: Vec<String> = Vec::new();
+        for slot in 1..frame {
+            let name: String = format!("v{}", slot);
+            let mentions = |text: &str| {
+                text.split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .any(|word| word == name)
+            };
+            // a slot is a parameter when its first mention is a read
+            let first: Option<bool> = function.lines.iter().find_map(|line| {
+                let line: &str = line.trim_start();
+                match line.split_once(" = ") {
+                    Some((target, value)) if target == name => Some(!mentions(value)),
+                    _ if mentions(line) => Some(false),
+                    _ => None,
+                }
+            });
+            if first == Some(false) {
+                parameters.push(name);
+            }
+        }
+
+        let mut body: String = if frame > 0 {
+            format!(
+                "fn {}({}) {{ // frame {}\n",
+                name,
+                parameters.join(", "),
+                frame
+            )
+        } else {
+            format!("fn {}() {{\n", name)
+        };
+        for line in &function.lines {
+            body.push_str(line);
+            body.push('\n');
+        }
+        body.push_str("}\n");
+        bodies.push(body);
+        globals.extend(function.globals);
+    }
+
+    let mut text: String = String::new();
+    for address in globals {
+        let initial: i64 = values.get(address as usize).copied().unwrap_or(0);
+        text.push_str(&format!("var g{} = {};\n", address, initial));
+    }
+    for body in bodies {
+        text.push('\n');
+        text.push_str(&body);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assemble;
+
+    #[test]
+    fn structures_loops_branches_and_calls() {
+        let values: Vec<i64> = assemble(
+            "
+                        rbo #stack
+                        in [count]
+            loop:       add #back, #0, [rb+0]
+                        add [count], #0, [rb+1]
+                        jnz #1, #double
+            back:       lt [rb+1], #10, [small]
+                        jz [small], #large
+                        out [rb+1]
+                        jz #0, #next
+            large:      out #-1
+            next:       add [count], #-1, [count]
+                        jnz [count], #loop
+                        hlt
+            double:     rbo #2
+                        mul [rb-1], #2, [rb-1]
+                        rbo #-2
+                        jz #0, [rb+0]
+            count:      .data 0
+            small:      .data 0
+            stack:      .data 0, 0, 0
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            decompile(&values),
+            "var g48 = 0;
+var g49 = 0;
+
+fn main() {
+    rb += 50;
+    g48 = input();
+    do {
+        sub_37(g48);
+        g49 = out1 < 10;
+        if (g49) {
+            output(out1);
+        } else {
+            output(-1);
+        }
+        g48 = g48 - 1;
+    } while (g48);
+    halt();
+}
+
+fn sub_37(v1) { // frame 2
+    v1 = v1 * 2;
+    return;
+}
+"
+        );
+    }
+
+    #[test]
+    fn names_patched_code_cells() {
+        let values: Vec<i64> = assemble(
+            "
+                    add #9, #0, [read+1]
+            read:   out [0]
+                    hlt
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            decompile(&values),
+            "\nfn main() {\n    patch_5 = 9;\n    output(mem[patch_5]);\n    halt();\n}\n"
+        );
+    }
+}
@@ -0,0 +1,369 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, StdinLock, Stdout, Write};
+use std::sync::mpsc::{Receiver, Sender};
+
+use crate::computer::{parse_values, StepResult};
+use crate::error::IntcodeError;
+
+// Where a computer's CPY instructions take their values from. None means
+// nothing is available (yet): the computer stops and reports NeedsInput.
+pub trait InputDevice {
+    fn read(&mut self) -> Result<Option<i64>, IntcodeError>;
+}
+
+// Where a computer's OUT instructions send their values.
+pub trait OutputDevice {
+    fn write(&mut self, value: i64) -> Result<(), IntcodeError>;
+}
+
+fn device_error(error: io::Error) -> IntcodeError {
+    IntcodeError::Device {
+        message: error.to_string(),
+    }
+}
+
+// Run `step` until the program halts or `input` runs dry. Input is only
+// pulled when a CPY needs it, so interactive devices see the output that
+// came before the question.
+pub(crate) fn drive<I, O>(
+    mut step: impl FnMut(&mut VecDeque<i64>) -> Result<StepResult, IntcodeError>,
+    input: &mut I,
+    output: &mut O,
+) -> Result<StepResult, IntcodeError>
+where
+    I: InputDevice + ?Sized,
+    O: OutputDevice + ?Sized,
+{
+    let mut queue: VecDeque<i64> = VecDeque::new();
+    loop {
+        match step(&mut queue)? {
+            StepResult::Executed => continue,
+            StepResult::Output(value) => output.write(value)?,
+            StepResult::NeedsInput => match input.read()? {
+                Some(value) => queue.push_back(value),
+                None => return Ok(StepResult::NeedsInput),
+            },
+            StepResult::Halted => return Ok(StepResult::Halted),
+        }
+    }
+}
+
+impl InputDevice for VecDeque<i64> {
+    fn read(&mut self) -> Result<Option<i64>, IntcodeError> {
+        Ok(self.pop_front())
+    }
+}
+
+// lets one computer's output queue feed another computer
+impl OutputDevice for VecDeque<i64> {
+    fn write(&mut self, value: i64) -> Result<(), IntcodeError> {
+        self.push_back(value);
+        Ok(())
+    }
+}
+
+impl OutputDevice for Vec<i64> {
+    fn write(&mut self, value: i64) -> Result<(), IntcodeError> {
+        self.push(value);
+        Ok(())
+    }
+}
+
+// Blocks until a value arrives. Input ends once every sender is gone.
+impl InputDevice for Receiver<i64> {
+    fn read(&mut self) -> Result<Option<i64>, IntcodeError> {
+        Ok(self.recv().ok())
+    }
+}
+
+impl OutputDevice for Sender<i64> {
+    fn write(&mut self, value: i64) -> Result<(), IntcodeError> {
+        self.send(value).map_err(|_| IntcodeError::Device {
+            message: "output channel closed".to_string(),
+        })
+    }
+}
+
+// Input produced by a closure, e.g. a robot's sensor reading.
+pub struct InputFn<F: FnMut() -> Option<i64>>(pub F);
+
+impl<F: FnMut() -> Option<i64>> InputDevice for InputFn<F> {
+    fn read(&mut self) -> Result<Option<i64>, IntcodeError> {
+        Ok((self.0)())
+    }
+}
+
+pub struct OutputFn<F: FnMut(i64)>(pub F);
+
+impl<F: FnMut(i64)> OutputDevice for OutputFn<F> {
+    fn write(&mut self, value: i64) -> Result<(), IntcodeError> {
+        (self.0)(value);
+        Ok(())
+    }
+}
+
+// Feeds text a line at a time, each character as its code followed by 10
+// for the newline. Input ends with the reader.
+pub struct AsciiInput<R: BufRead> {
+    reader: R,
+    pending: VecDeque<i64>,
+}
+
+impl<R: BufRead> AsciiInput<R> {
+    pub fn new(reader: R) -> Self {
+        AsciiInput {
+            reader,
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+impl AsciiInput<StdinLock<'static>> {
+    pub fn stdin() -> Self {
+        AsciiInput::new(io::stdin().lock())
+    }
+}
+
+impl AsciiInput<BufReader<File>> {
+    pub fn open(filename: &str) -> Result<Self, IntcodeError> {
+        let file: File =
+            File::open(filename).map_err(|error| IntcodeError::reading(filename, error))?;
+        Ok(AsciiInput::new(BufReader::new(file)))
+    }
+}
+
+impl<R: BufRead> InputDevice for AsciiInput<R> {
+    fn read(&mut self) -> Result<Option<i64>, IntcodeError> {
+        if self.pending.is_empty() {
+            let mut line: String = String::new();
+            if self.reader.read_line(&mut line).map_err(device_error)? == 0 {
+                return Ok(None);
+            }
+            let line: &str = line.trim_end_matches(['\r', '\n']);
+            self.pending.extend(line.chars().map(|c| c as i64));
+            self.pending.push_back(10);
+        }
+        Ok(self.pending.pop_front())
+    }
+}
+
+// Prints ASCII output as text. Values outside the ASCII range, like the
+// final answers of the ASCII-capable days, go on a line of their own.
+pub struct AsciiOutput<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> AsciiOutput<W> {
+    pub fn new(writer: W) -> Self {
+        AsciiOutput { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl AsciiOutput<Stdout> {
+    pub fn stdout() -> Self {
+        AsciiOutput::new(io::stdout())
+    }
+}
+
+impl AsciiOutput<BufWriter<File>> {
+    pub fn create(filename: &str) -> Result<Self, IntcodeError> {
+        let file: File = File::create(filename).map_err(|_| IntcodeError::FileNotWritable {
+            filename: filename.to_string(),
+        })?;
+        Ok(AsciiOutput::new(BufWriter::new(file)))
+    }
+}
+
+impl<W: Write> OutputDevice for AsciiOutput<W> {
+    fn write(&mut self, value: i64) -> Result<(), IntcodeError> {
+        match value {
+            0..=127 => {
+                self.writer
+                    .write_all(&[value as u8])
+                    .map_err(device_error)?;
+                if value == 10 {
+                    self.writer.flush().map_err(device_error)?;
+                }
+                Ok(())
+            }
+            _ => writeln!(self.writer, "{}", value).map_err(device_error),
+        }
+    }
+}
+
+// Reads integers, one or more comma-separated per line.
+pub struct NumberInput<R: BufRead> {
+    reader: R,
+    pending: VecDeque<i64>,
+}
+
+impl<R: BufRead> NumberInput<R> {
+    pub fn new(reader: R) -> Self {
+        NumberInput {
+            reader,
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+impl NumberInput<StdinLock<'static>> {
+    pub fn stdin() -> Self {
+        NumberInput::new(io::stdin().lock())
+    }
+}
+
+impl NumberInput<BufReader<File>> {
+    pub fn open(filename: &str) -> Result<Self, IntcodeError> {
+        let file: File =
+            File::open(filename).map_err(|error| IntcodeError::reading(filename, error))?;
+        Ok(NumberInput::new(BufReader::new(file)))
+    }
+}
+
+impl<R: BufRead> InputDevice for NumberInput<R> {
+    fn read(&mut self) -> Result<Option<i64>, IntcodeError> {
+        while self.pending.is_empty() {
+            let mut line: String = String::new();
+            if self.reader.read_line(&mut line).map_err(device_error)? == 0 {
+                return Ok(None);
+            }
+            if !line.trim().is_empty() {
+                self.pending.extend(parse_values(&line)?);
+            }
+        }
+        Ok(self.pending.pop_front())
+    }
+}
+
+// Writes every value on a line of its own.
+pub struct NumberOutput<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> NumberOutput<W> {
+    pub fn new(writer: W) -> Self {
+        NumberOutput { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl NumberOutput<Stdout> {
+    pub fn stdout() -> Self {
+        NumberOutput::new(io::stdout())
+    }
+}
+
+impl NumberOutput<BufWriter<File>> {
+    pub fn create(filename: &str) -> Result<Self, IntcodeError> {
+        let file: File = File::create(filename).map_err(|_| IntcodeError::FileNotWritable {
+            filename: filename.to_string(),
+        })?;
+        Ok(NumberOutput::new(BufWriter::new(file)))
+    }
+}
+
+impl<W: Write> OutputDevice for NumberOutput<W> {
+    fn write(&mut self, value: i64) -> Result<(), IntcodeError> {
+        writeln!(self.writer, "{}", value).map_err(device_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assemble, IntcodeComputer, Memory};
+    use std::io::Cursor;
+    use std::sync::mpsc;
+    use std::thread;
+
+    // doubles every value it reads, forever
+    fn doubler() -> IntcodeComputer {
+        let values: Vec<i64> =
+            assemble("loop: in [100]\nmul [100], #2, [100]\nout [100]\njnz #1, #loop").unwrap();
+        IntcodeComputer::new(Memory::new(values))
+    }
+
+    #[test]
+    fn queues_and_closures() {
+        let mut computer: IntcodeComputer = doubler();
+        let mut output: Vec<i64> = Vec::new();
+        let result: StepResult = computer
+            .run_devices(&mut VecDeque::from([1, 2, 3]), &mut output)
+            .unwrap();
+        assert_eq!(result, StepResult::NeedsInput);
+        assert_eq!(output, vec![2, 4, 6]);
+
+        let mut next: i64 = 0;
+        let mut input = InputFn(|| {
+            next += 1;
+            (next <= 2).then_some(next * 10)
+        });
+        let mut seen: Vec<i64> = Vec::new();
+        doubler()
+            .run_devices(&mut input, &mut OutputFn(|value| seen.push(value)))
+            .unwrap();
+        assert_eq!(seen, vec![20, 40]);
+    }
+
+    #[test]
+    fn channels_connect_computers() {
+        let (sender, mut first_input) = mpsc::channel::<i64>();
+        let (mut first_output, mut second_input) = mpsc::channel::<i64>();
+        let (mut second_output, results) = mpsc::channel::<i64>();
+        let first = thread::spawn(move || {
+            doubler()
+                .run_devices(&mut first_input, &mut first_output)
+                .unwrap()
+        });
+        let second = thread::spawn(move || {
+            doubler()
+                .run_devices(&mut second_input, &mut second_output)
+                .unwrap()
+        });
+        for value in [1, 5, -3] {
+            sender.send(value).unwrap();
+        }
+        drop(sender);
+        assert_eq!(first.join().unwrap(), StepResult::NeedsInput);
+        assert_eq!(second.join().unwrap(), StepResult::NeedsInput);
+        assert_eq!(results.iter().collect::<Vec<i64>>(), vec![4, 20, -12]);
+    }
+
+    #[test]
+    fn ascii_lines_in_and_out() {
+        // echo a line, then print 1000
+        let values: Vec<i64> = assemble(
+            "
+            loop:   in [100]
+                    out [100]
+                    eq [100], #10, [101]
+                    jz [101], #loop
+                    out #1000
+                    hlt
+            ",
+        )
+        .unwrap();
+        let mut computer = IntcodeComputer::new(Memory::new(values));
+        let mut input = AsciiInput::new(Cursor::new("NOT A J\r\nWALK\n"));
+        let mut output = AsciiOutput::new(Vec::new());
+        let result: StepResult = computer.run_devices(&mut input, &mut output).unwrap();
+        assert_eq!(result, StepResult::Halted);
+        assert_eq!(
+            String::from_utf8(output.into_inner()).unwrap(),
+            "NOT A J\n1000\n"
+        );
+        assert_eq!(input.read().unwrap(), Some('W' as i64));
+
+        let mut numbers = NumberInput::new(Cursor::new("1, 2\n\n-3\n"));
+        let read: Vec<Option<i64>> = (0..4).map(|_| numbers.read().unwrap()).collect();
+        assert_eq!(read, vec![Some(1), Some(2), Some(-3), None]);
+    }
+}
@@ -0,0 +1,336 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::computer::{parse_instruction, Operation, OperationType, ParameterMode};
+
+const DATA_PER_LINE: usize = 8;
+const MIN_STRING_LENGTH: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub address: usize,
+    pub operation: Operation,
+    pub parameters: Vec<i64>,
+}
+
+impl Instruction {
+    // number of memory cells the instruction takes, word included
+    pub fn size(&self) -> usize {
+        1 + self.parameters.len()
+    }
+
+    // immediate jump target of a jnz/jz
+    pub fn jump_target(&self) -> Option<i64> {
+        match self.operation.operation {
+            OperationType::JIT | OperationType::JIF
+                if self.operation.second_parameter_mode == ParameterMode::ImmediateMode =>
+            {
+                Some(self.parameters[1])
+            }
+            _ => None,
+        }
+    }
+
+    // true when execution can never continue with the next instruction
+    pub fn ends_flow(&self) -> bool {
+        let condition: Option<i64> =
+            if self.operation.first_parameter_mode == ParameterMode::ImmediateMode {
+                self.parameters.first().copied()
+            } else {
+                None
+            };
+        match self.operation.operation {
+            OperationType::END => true,
+            OperationType::JIT => matches!(condition, Some(value) if value != 0),
+            OperationType::JIF => condition == Some(0),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let operands: Vec<String> = self
+            .parameters
+            .iter()
+            .enumerate()
+            .map(|(index, value)| format_operand(self.operation.parameter_mode(index + 1), *value))
+            .collect();
+
+        if operands.is_empty() {
+            write!(f, "{}", self.operation.operation.mnemonic())
+        } else {
+            write!(
+                f,
+                "{} {}",
+                self.operation.operation.mnemonic(),
+                operands.join(", ")
+            )
+        }
+    }
+}
+
+pub fn format_operand(parameter_mode: ParameterMode, value: i64) -> String {
+    match parameter_mode {
+        ParameterMode::PositionMode => format!("[{}]", value),
+        ParameterMode::ImmediateMode => format!("#{}", value),
+        ParameterMode::RelativeMode if value < 0 => format!("[rb-{}]", -value),
+        ParameterMode::RelativeMode => format!("[rb+{}]", value),
+    }
+}
+
+// Instruction word for `operation`. Modes of unused parameters are left out.
+pub fn encode(operation: &Operation) -> i64 {
+    let mut instruction: i64 = operation.operation as i64;
+    let mut scale: i64 = 100;
+    for parameter in 1..=operation.operation.parameters() {
+        instruction += operation.parameter_mode(parameter) as i64 * scale;
+        scale *= 10;
+    }
+    instruction
+}
+
+// Decode the instruction at `address`. Only words that encode back to
+// themselves and whose parameters fit in the image are accepted, so that
+// data which merely looks like an opcode is not mistaken for code.
+pub fn decode(values: &[i64], address: usize) -> Option<Instruction> {
+    let operation: Operation = parse_instruction(address as i64, *values.get(address)?).ok()?;
+    let parameters: usize = operation.operation.parameters();
+
+    if address + parameters >= values.len() || encode(&operation) != values[address] {
+        return None;
+    }
+    Some(Instruction {
+        address,
+        operation,
+        parameters: values[address + 1..=address + parameters].to_vec(),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Line {
+    Code(Instruction),
+    Data { address: usize, values: Vec<i64> },
+    Text { address: usize, text: String },
+}
+
+impl Line {
+    pub fn address(&self) -> usize {
+        match self {
+            Line::Code(instruction) => instruction.address,
+            Line::Data { address, .. } | Line::Text { address, .. } => *address,
+        }
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Line::Code(instruction) => write!(f, "{}", instruction),
+            Line::Data { values, .. } => {
+                let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+                write!(f, ".data {}", values.join(", "))
+            }
+            Line::Text { text, .. } => write!(f, ".string {}", escape(text)),
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped: String = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '\n' => escaped.push_str("\\n"),
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+fn is_text(value: i64) -> bool {
+    value == 10 || (32..127).contains(&value)
+}
+
+// Addresses of `value` when it is a constant being moved around by an add or
+// mul (`add #x, #0, ..` or `mul #x, #1, ..`). That is how programs push
+// return addresses, so these are treated as possible code entry points.
+pub(crate) fn moved_constant(instruction: &Instruction) -> Option<i64> {
+    let operation: &Operation = &instruction.operation;
+    if operation.first_parameter_mode != ParameterMode::ImmediateMode
+        || operation.second_parameter_mode != ParameterMode::ImmediateMode
+    {
+        return None;
+    }
+    let (a, b) = (instruction.parameters[0], instruction.parameters[1]);
+    match operation.operation {
+        OperationType::SUM if b == 0 => Some(a),
+        OperationType::SUM if a == 0 => Some(b),
+        OperationType::MUL if b == 1 => Some(a),
+        OperationType::MUL if a == 1 => Some(b),
+        _ => None,
+    }
+}
+
+// Follow control flow from address 0 and mark where instructions start.
+// Returns those starts plus, for every immediate jump target, the addresses
+// of the jumps that lead there.
+pub fn find_code(values: &[i64]) -> (Vec<bool>, BTreeMap<usize, Vec<usize>>) {
+    let mut starts: Vec<bool> = vec![false; values.len()];
+    let mut covered: Vec<bool> = vec![false; values.len()];
+    let mut jumps: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    let mut entries: Vec<usize> = vec![0];
+
+    while let Some(entry) = entries.pop() {
+        let mut address: usize = entry;
+        while address < values.len() && !covered[address] {
+            let instruction: Instruction = match decode(values, address) {
+                Some(instruction) => instruction,
+                None => break,
+            };
+            starts[address] = true;
+            covered[address..address + instruction.size()].fill(true);
+
+            let targets = instruction.jump_target().into_iter();
+            for target in targets.chain(moved_constant(&instruction)) {
+                if target >= 0 && (target as usize) < values.len() {
+                    if instruction.jump_target() == Some(target) {
+                        jumps.entry(target as usize).or_default().push(address);
+                    }
+                    entries.push(target as usize);
+                }
+            }
+            if instruction.ends_flow() {
+                break;
+            }
+            address += instruction.size();
+        }
+    }
+    (starts, jumps)
+}
+
+// Split a run of data cells into text and plain data lines.
+fn data_lines(values: &[i64], start: usize, end: usize, lines: &mut Vec<Line>) {
+    let mut address: usize = start;
+    while address < end {
+        let text_end: usize = (address..end)
+            .find(|index| !is_text(values[*index]))
+            .unwrap_or(end);
+        if text_end - address >= MIN_STRING_LENGTH {
+            let text: String = values[address..text_end]
+                .iter()
+                .map(|value| *value as u8 as char)
+                .collect();
+            lines.push(Line::Text { address, text });
+            address = text_end;
+            continue;
+        }
+
+        // plain data up to the next string long enough to show as text
+        let mut data_end: usize = address + 1;
+        while data_end < end && data_end - address < DATA_PER_LINE {
+            let run: usize = (data_end..end)
+                .take_while(|index| is_text(values[*index]))
+                .count();
+            if run >= MIN_STRING_LENGTH {
+                break;
+            }
+            data_end += 1;
+        }
+        lines.push(Line::Data {
+            address,
+            values: values[address..data_end].to_vec(),
+        });
+        address = data_end;
+    }
+}
+
+pub fn disassemble(values: &[i64]) -> Vec<Line> {
+    let (starts, _) = find_code(values);
+    let mut lines: Vec<Line> = Vec::new();
+
+    let mut address: usize = 0;
+    while address < values.len() {
+        if starts[address] {
+            let instruction: Instruction = decode(values, address).unwrap();
+            address += instruction.size();
+            lines.push(Line::Code(instruction));
+            continue;
+        }
+        let data_end: usize = (address..values.len())
+            .find(|index| starts[*index])
+            .unwrap_or(values.len());
+        data_lines(values, address, data_end, &mut lines);
+        address = data_end;
+    }
+    lines
+}
+
+// Annotated listing: one line per instruction or data run, prefixed with its
+// address, and a comment on every immediate jump target.
+pub fn listing(values: &[i64]) -> String {
+    let (_, jumps) = find_code(values);
+    let mut listing: String = String::new();
+
+    for line in disassemble(values) {
+        let mut text: String = format!("{:>5}: {}", line.address(), line);
+        if let Some(sources) = jumps.get(&line.address()) {
+            let sources: Vec<String> = sources.iter().map(|source| source.to_string()).collect();
+            text = format!("{:<40} ; from {}", text, sources.join(", "));
+        }
+        listing.push_str(&text);
+        listing.push('\n');
+    }
+    listing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operands_are_rendered_per_mode() {
+        let values: Vec<i64> = vec![21101, 3, -4, -2, 99];
+        let instruction: Instruction = decode(&values, 0).unwrap();
+        assert_eq!(instruction.to_string(), "add #3, #-4, [rb-2]");
+
+        let values: Vec<i64> = vec![1, 5, 6, 7, 99];
+        assert_eq!(decode(&values, 0).unwrap().to_string(), "add [5], [6], [7]");
+    }
+
+    #[test]
+    fn non_canonical_words_are_not_code() {
+        // OUT has a single parameter, so a mode digit for a second one is junk
+        assert_eq!(decode(&[1104, 1, 99], 0), None);
+        // parameters running past the end of the image
+        assert_eq!(decode(&[1, 0, 0], 0), None);
+    }
+
+    #[test]
+    fn unreachable_cells_are_data() {
+        // jump over a string, print its first character, halt
+        let mut values: Vec<i64> = vec![1105, 1, 8];
+        values.extend("hello".chars().map(|c| c as i64));
+        values.extend([4, 3, 99]);
+
+        let lines: Vec<String> = disassemble(&values)
+            .iter()
+            .map(|line| line.to_string())
+            .collect();
+        assert_eq!(
+            lines,
+            vec!["jnz #1, #8", ".string \"hello\"", "out [3]", "hlt"]
+        );
+    }
+
+    #[test]
+    fn listing_shows_addresses_and_jump_sources() {
+        let values: Vec<i64> = vec![1105, 1, 4, 0, 99];
+        assert_eq!(
+            listing(&values),
+            "    0: jnz #1, #4\n    3: .data 0\n    4: hlt                               ; from 0\n"
+        );
+    }
+}
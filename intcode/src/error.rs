@@ -0,0 +1,188 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntcodeError {
+    // opcode (instruction % 100) is not one of the known operations
+    UnknownOperation {
+        pointer: i64,
+        instruction: i64,
+    },
+    // mode digit of parameter `parameter` (1-based) is not 0, 1 or 2
+    UnknownParameterMode {
+        pointer: i64,
+        instruction: i64,
+        parameter: usize,
+    },
+    // parameter `parameter` is written to but uses immediate mode
+    ImmediateModeWrite {
+        pointer: i64,
+        instruction: i64,
+        parameter: usize,
+    },
+    // memory below address 0 was read or written
+    NegativeAddress {
+        pointer: i64,
+        address: i64,
+    },
+    // comma-separated token at `position` is not an integer
+    InvalidToken {
+        position: usize,
+        token: String,
+    },
+    FileNotFound {
+        filename: String,
+    },
+    FileNotWritable {
+        filename: String,
+    },
+    // file exists but could not be read
+    Io {
+        filename: String,
+        kind: io::ErrorKind,
+    },
+    // trace could not be written or read back
+    Trace {
+        message: String,
+    },
+    // snapshot data is malformed at `line` (1-based)
+    InvalidSnapshot {
+        line: usize,
+        message: String,
+    },
+    // assembler source is malformed at `line` (1-based)
+    Assembly {
+        line: usize,
+        message: String,
+    },
+    // native code could not be generated for this host
+    Jit {
+        message: String,
+    },
+    // springscript is malformed at `line` (1-based)
+    Springscript {
+        line: usize,
+        message: String,
+    },
+    // value is not an ASCII character where text was expected
+    InvalidAscii {
+        value: i64,
+    },
+    // an input or output device failed
+    Device {
+        message: String,
+    },
+    // packet sent to an address with no node behind it
+    UnknownAddress {
+        address: i64,
+    },
+    // every node is waiting for input and the NAT has nothing to send
+    NetworkIdle,
+    // a node or worker thread stopped the network from making progress
+    Deadlock {
+        message: String,
+    },
+    // execution log does not match the network being replayed
+    Replay {
+        message: String,
+    },
+    // program or search finished without producing an answer
+    NoSolution {
+        message: String,
+    },
+    // puzzle configuration file is malformed
+    InvalidConfig {
+        filename: String,
+        message: String,
+    },
+}
+
+impl IntcodeError {
+    // Error for a file that could not be opened or read, keeping what went
+    // wrong unless the file is simply missing.
+    pub fn reading(filename: &str, error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::NotFound => IntcodeError::FileNotFound {
+                filename: filename.to_string(),
+            },
+            kind => IntcodeError::Io {
+                filename: filename.to_string(),
+                kind,
+            },
+        }
+    }
+}
+
+impl fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IntcodeError::UnknownOperation {
+                pointer,
+                instruction,
+            } => write!(
+                f,
+                "unknown operation {} at pointer {} (instruction {})",
+                instruction % 100,
+                pointer,
+                instruction
+            ),
+            IntcodeError::UnknownParameterMode {
+                pointer,
+                instruction,
+                parameter,
+            } => write!(
+                f,
+                "unknown mode for parameter {} at pointer {} (instruction {})",
+                parameter, pointer, instruction
+            ),
+            IntcodeError::ImmediateModeWrite {
+                pointer,
+                instruction,
+                parameter,
+            } => write!(
+                f,
+                "parameter {} is written to in immediate mode at pointer {} (instruction {})",
+                parameter, pointer, instruction
+            ),
+            IntcodeError::NegativeAddress { pointer, address } => {
+                write!(f, "negative address {} at pointer {}", address, pointer)
+            }
+            IntcodeError::InvalidToken { position, token } => {
+                write!(f, "invalid token {:?} at position {}", token, position)
+            }
+            IntcodeError::FileNotFound { filename } => write!(f, "file not found: {}", filename),
+            IntcodeError::FileNotWritable { filename } => {
+                write!(f, "file not writable: {}", filename)
+            }
+            IntcodeError::Io { filename, kind } => {
+                write!(f, "could not read {}: {}", filename, kind)
+            }
+            IntcodeError::Trace { message } => write!(f, "trace error: {}", message),
+            IntcodeError::InvalidSnapshot { line, message } => {
+                write!(f, "invalid snapshot at line {}: {}", line, message)
+            }
+            IntcodeError::Assembly { line, message } => write!(f, "line {}: {}", line, message),
+            IntcodeError::Jit { message } => write!(f, "jit error: {}", message),
+            IntcodeError::Springscript { line, message } => {
+                write!(f, "springscript line {}: {}", line, message)
+            }
+            IntcodeError::InvalidAscii { value } => {
+                write!(f, "value {} is not an ascii character", value)
+            }
+            IntcodeError::Device { message } => write!(f, "device error: {}", message),
+            IntcodeError::UnknownAddress { address } => {
+                write!(f, "no node at address {}", address)
+            }
+            IntcodeError::NetworkIdle => write!(f, "network is idle with nothing left to send"),
+            IntcodeError::Deadlock { message } => write!(f, "deadlock: {}", message),
+            IntcodeError::Replay { message } => write!(f, "replay diverged: {}", message),
+            IntcodeError::NoSolution { message } => write!(f, "no solution: {}", message),
+            IntcodeError::InvalidConfig { filename, message } => {
+                write!(f, "invalid config {}: {}", filename, message)
+            }
+        }
+    }
+}
+
+impl Error for IntcodeError {}
@@ -0,0 +1,444 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use crate::computer::{IntcodeComputer, StepResult};
+use crate::error::IntcodeError;
+use crate::memory::Memory;
+use crate::network::{Nat, NatAction, Packet, NAT_ADDRESS};
+
+// Node to run until it next waits for input, with the values it reads
+// first. With none it reads -1, like a NIC with no packets waiting.
+struct Run {
+    node: usize,
+    input: Vec<i64>,
+}
+
+// What a node wrote during a run, and whether it halted.
+struct Report {
+    node: usize,
+    result: Result<(Vec<i64>, bool), IntcodeError>,
+}
+
+// The coordinator's view of a node.
+#[derive(Debug, Clone, Default)]
+struct NodeState {
+    queue: VecDeque<i64>,
+    // start of the next packet
+    sending: Vec<i64>,
+    running: bool,
+    // last run read -1 and sent nothing: blocked until a packet arrives
+    waiting: bool,
+    halted: bool,
+}
+
+// Order in which the coordinator handled the nodes' reports. Everything
+// else it does follows from that order, so running the network again with
+// the same log gives the same result, however the threads are scheduled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionLog {
+    order: Vec<usize>,
+}
+
+impl ExecutionLog {
+    pub fn order(&self) -> &[usize] {
+        &self.order
+    }
+
+    pub fn save(&self, filename: &str) -> Result<(), IntcodeError> {
+        let data: String = self
+            .order
+            .iter()
+            .map(|node| format!("{}\n", node))
+            .collect();
+        fs::write(filename, data).map_err(|_| IntcodeError::FileNotWritable {
+            filename: filename.to_string(),
+        })
+    }
+
+    // Log saved by `save`, one node per line.
+    pub fn load(filename: &str) -> Result<Self, IntcodeError> {
+        let data: String =
+            fs::read_to_string(filename).map_err(|error| IntcodeError::reading(filename, error))?;
+        let order: Vec<usize> = data
+            .lines()
+            .enumerate()
+            .map(|(position, line)| {
+                line.trim().parse().map_err(|_| IntcodeError::InvalidToken {
+                    position,
+                    token: line.to_string(),
+                })
+            })
+            .collect::<Result<Vec<usize>, IntcodeError>>()?;
+        Ok(ExecutionLog { order })
+    }
+}
+
+// Run `computer` until it waits for input or halts, giving up after
+// `max_steps` instructions.
+fn run_node(
+    node: usize,
+    computer: &mut IntcodeComputer,
+    input: Vec<i64>,
+    max_steps: Option<usize>,
+) -> Result<(Vec<i64>, bool), IntcodeError> {
+    let mut queue: VecDeque<i64> = if input.is_empty() {
+        VecDeque::from([-1])
+    } else {
+        VecDeque::from(input)
+    };
+    let mut output: Vec<i64> = Vec::new();
+    let mut steps: usize = 0;
+    loop {
+        if max_steps.is_some_and(|max_steps| steps >= max_steps) {
+            return Err(IntcodeError::Deadlock {
+                message: format!(
+                    "node {} ran {} steps without waiting for input",
+                    node, steps
+                ),
+            });
+        }
+        steps += 1;
+        match computer.step(&mut queue)? {
+            StepResult::Executed => continue,
+            StepResult::Output(value) => output.push(value),
+            StepResult::NeedsInput => return Ok((output, false)),
+            StepResult::Halted => return Ok((output, true)),
+        }
+    }
+}
+
+// Worker thread owning every `threads`-th node, starting with `first`.
+fn work(
+    first: usize,
+    threads: usize,
+    mut computers: Vec<IntcodeComputer>,
+    max_steps: Option<usize>,
+    runs: Receiver<Run>,
+    reports: Sender<Report>,
+) {
+    for Run { node, input } in runs {
+        let computer: &mut IntcodeComputer = &mut computers[(node - first) / threads];
+        let result: Result<(Vec<i64>, bool), IntcodeError> =
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                run_node(node, computer, input, max_steps)
+            }))
+            .unwrap_or_else(|_| {
+                Err(IntcodeError::Deadlock {
+                    message: format!("worker for node {} panicked", node),
+                })
+            });
+        if reports.send(Report { node, result }).is_err() {
+            return;
+        }
+    }
+}
+
+fn workers_stopped() -> IntcodeError {
+    IntcodeError::Deadlock {
+        message: "worker threads stopped".to_string(),
+    }
+}
+
+fn replay_error(message: String) -> IntcodeError {
+    IntcodeError::Replay { message }
+}
+
+// The day23 network on a pool of worker threads. The calling thread
+// routes every packet and decides which nodes run next, so it always knows
+// when all of them are blocked on empty queues: that is when the network
+// is idle and the NAT gets to wake it.
+#[derive(Debug, Clone)]
+pub struct ThreadedNetwork {
+    program: Memory,
+    size: usize,
+    threads: usize,
+    max_steps: Option<usize>,
+}
+
+impl ThreadedNetwork {
+    pub fn new(program: Memory, size: usize, threads: usize) -> Self {
+        ThreadedNetwork {
+            program,
+            size,
+            threads: threads.clamp(1, size.max(1)),
+            max_steps: None,
+        }
+    }
+
+    // A node that never gets back to reading input would hold the whole
+    // network up. With a limit it is reported as a deadlock instead.
+    pub fn with_step_limit(program: Memory, size: usize, threads: usize, max_steps: usize) -> Self {
+        ThreadedNetwork {
+            max_steps: Some(max_steps),
+            ..ThreadedNetwork::new(program, size, threads)
+        }
+    }
+
+    // Run until the NAT says to stop. Returns its value and the log to
+    // replay the run with.
+    pub fn run(&self, nat: &mut impl Nat) -> Result<(i64, ExecutionLog), IntcodeError> {
+        let (value, order): (i64, Vec<usize>) = self.execute(nat, None)?;
+        Ok((value, ExecutionLog { order }))
+    }
+
+    // Run again, handling reports in the order of `log`.
+    pub fn replay(&self, nat: &mut impl Nat, log: &ExecutionLog) -> Result<i64, IntcodeError> {
+        let (value, _): (i64, Vec<usize>) = self.execute(nat, Some(&log.order))?;
+        Ok(value)
+    }
+
+    fn execute(
+        &self,
+        nat: &mut impl Nat,
+        replay: Option<&[usize]>,
+    ) -> Result<(i64, Vec<usize>), IntcodeError> {
+        thread::scope(|scope| {
+            let (report_sender, reports) = mpsc::channel::<Report>();
+            let mut runs: Vec<Sender<Run>> = Vec::new();
+            for first in 0..self.threads {
+                let (run_sender, run_receiver) = mpsc::channel::<Run>();
+                let computers: Vec<IntcodeComputer> = (first..self.size)
+                    .step_by(self.threads)
+                    .map(|_| IntcodeComputer::new(self.program.clone()))
+                    .collect();
+                let (threads, max_steps) = (self.threads, self.max_steps);
+                let report_sender: Sender<Report> = report_sender.clone();
+                scope.spawn(move || {
+                    work(
+                        first,
+                        threads,
+                        computers,
+                        max_steps,
+                        run_receiver,
+                        report_sender,
+                    )
+                });
+                runs.push(run_sender);
+            }
+            drop(report_sender);
+            // workers finish once their runs are done and the senders gone
+            self.coordinate(nat, replay, &runs, &reports)
+        })
+    }
+
+    fn coordinate(
+        &self,
+        nat: &mut impl Nat,
+        replay: Option<&[usize]>,
+        runs: &[Sender<Run>],
+        reports: &Receiver<Report>,
+    ) -> Result<(i64, Vec<usize>), IntcodeError> {
+        let mut nodes: Vec<NodeState> = (0..self.size)
+            .map(|address| NodeState {
+                queue: VecDeque::from([address as i64]),
+                ..NodeState::default()
+            })
+            .collect();
+        let mut ready: Vec<usize> = (0..self.size).collect();
+        let mut running: usize = 0;
+        let mut order: Vec<usize> = Vec::new();
+        // reports that came in ahead of their turn in the replay log
+        let mut early: Vec<Option<Report>> = (0..self.size).map(|_| None).collect();
+
+        let deliver = |nodes: &mut Vec<NodeState>, ready: &mut Vec<usize>, packet: Packet| {
+            let address: usize = usize::try_from(packet.address)
+                .ok()
+                .filter(|address| *address < nodes.len())
+                .ok_or(IntcodeError::UnknownAddress {
+                    address: packet.address,
+                })?;
+            nodes[address].queue.extend([packet.x, packet.y]);
+            ready.push(address);
+            Ok::<(), IntcodeError>(())
+        };
+
+        loop {
+            for node in ready.drain(..) {
+                let state: &mut NodeState = &mut nodes[node];
+                if state.running || state.halted {
+                    continue;
+                }
+                let input: Vec<i64> = state.queue.drain(..).collect();
+                state.waiting = input.is_empty();
+                state.running = true;
+                running += 1;
+                runs[node % self.threads]
+                    .send(Run { node, input })
+                    .map_err(|_| workers_stopped())?;
+            }
+
+            if running == 0 {
+                match nat.idle() {
+                    NatAction::Continue => return Err(IntcodeError::NetworkIdle),
+                    NatAction::Send(packet) => deliver(&mut nodes, &mut ready, packet)?,
+                    NatAction::Stop(value) => return Ok((value, order)),
+                }
+                continue;
+            }
+
+            let report: Report = match replay {
+                None => reports.recv().map_err(|_| workers_stopped())?,
+                Some(log) => {
+                    let node: usize = *log.get(order.len()).ok_or_else(|| {
+                        replay_error("log ended before the network stopped".to_string())
+                    })?;
+                    if !nodes.get(node).is_some_and(|state| state.running) {
+                        return Err(replay_error(format!(
+                            "node {} is not running at entry {}",
+                            node,
+                            order.len() + 1
+                        )));
+                    }
+                    while early[node].is_none() {
+                        let report: Report = reports.recv().map_err(|_| workers_stopped())?;
+                        let from: usize = report.node;
+                        early[from] = Some(report);
+                    }
+                    early[node].take().unwrap()
+                }
+            };
+            order.push(report.node);
+            let (output, halted): (Vec<i64>, bool) = report.result?;
+
+            let state: &mut NodeState = &mut nodes[report.node];
+            running -= 1;
+            state.running = false;
+            state.halted = halted;
+            state.waiting &= output.is_empty();
+            state.sending.extend(output);
+            if !state.halted && (!state.waiting || !state.queue.is_empty()) {
+                ready.push(report.node);
+            }
+            let complete: usize = state.sending.len() / 3 * 3;
+            let packets: Vec<Packet> = state
+                .sending
+                .drain(..complete)
+                .collect::<Vec<i64>>()
+                .chunks(3)
+                .map(|values| Packet {
+                    address: values[0],
+                    x: values[1],
+                    y: values[2],
+                })
+                .collect();
+
+            for packet in packets {
+                if packet.address != NAT_ADDRESS {
+                    deliver(&mut nodes, &mut ready, packet)?;
+                    continue;
+                }
+                match nat.receive(packet) {
+                    NatAction::Continue => {}
+                    NatAction::Send(packet) => deliver(&mut nodes, &mut ready, packet)?,
+                    NatAction::Stop(value) => return Ok((value, order)),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assemble, FirstPacket, WakeOnIdle};
+
+    // node 0 starts a packet down the line with X 0, every node adds its
+    // address to Y while X is 0 and passes it on, skipping the NAT's address,
+    // and the last one sends it to the NAT with X 1
+    fn chain(size: usize) -> Memory {
+        let values: Vec<i64> = assemble(&format!(
+            "
+                    in [100]
+                    jnz [100], #listen
+                    out #1
+                    out #0
+                    out #0
+            listen: in [101]
+                    eq [101], #-1, [102]
+                    jnz [102], #listen
+                    in [103]
+                    eq [101], #0, [105]
+                    mul [105], [100], [105]
+                    add [103], [105], [103]
+                    add [100], #1, [104]
+                    eq [104], #255, [102]
+                    jz [102], #last
+                    add [104], #1, [104]
+            last:   eq [104], #{}, [102]
+                    jz [102], #send
+                    add #255, #0, [104]
+                    add #1, #0, [101]
+            send:   out [104]
+                    out [101]
+                    out [103]
+                    jnz #1, #listen
+            ",
+            size
+        ))
+        .unwrap();
+        Memory::new(values)
+    }
+
+    #[test]
+    fn runs_thousands_of_nodes() {
+        let network = ThreadedNetwork::new(chain(2000), 2000, 8);
+        let (value, _) = network.run(&mut FirstPacket).unwrap();
+        assert_eq!(value, 1999 * 2000 / 2 - 255);
+
+        // the NAT sends the packet round again from node 0, and gets the
+        // same Y back
+        let network = ThreadedNetwork::new(chain(50), 50, 4);
+        let (value, _) = network.run(&mut WakeOnIdle::default()).unwrap();
+        assert_eq!(value, 49 * 50 / 2);
+    }
+
+    #[test]
+    fn replays_the_log() {
+        let network = ThreadedNetwork::new(chain(100), 100, 4);
+        let (value, log) = network.run(&mut WakeOnIdle::default()).unwrap();
+
+        let filename = std::env::temp_dir().join(format!("intcode-{}.log", std::process::id()));
+        let filename: &str = filename.to_str().unwrap();
+        log.save(filename).unwrap();
+        let loaded: ExecutionLog = ExecutionLog::load(filename).unwrap();
+        fs::remove_file(filename).unwrap();
+        assert_eq!(loaded, log);
+
+        // the number of threads makes no difference
+        let replayed = ThreadedNetwork::new(chain(100), 100, 3);
+        assert_eq!(
+            replayed.replay(&mut WakeOnIdle::default(), &loaded),
+            Ok(value)
+        );
+
+        let truncated = ExecutionLog {
+            order: log.order()[..10].to_vec(),
+        };
+        assert_eq!(
+            replayed.replay(&mut WakeOnIdle::default(), &truncated),
+            Err(IntcodeError::Replay {
+                message: "log ended before the network stopped".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn detects_idle_and_stuck_networks() {
+        let silent: Memory = Memory::new(assemble("loop: in [100]\njnz #1, #loop").unwrap());
+        assert_eq!(
+            ThreadedNetwork::new(silent, 10, 2).run(&mut WakeOnIdle::default()),
+            Err(IntcodeError::NetworkIdle)
+        );
+
+        let stuck: Memory = Memory::new(assemble("in [100]\nloop: jnz #1, #loop").unwrap());
+        assert_eq!(
+            ThreadedNetwork::with_step_limit(stuck, 1, 1, 1000).run(&mut FirstPacket),
+            Err(IntcodeError::Deadlock {
+                message: "node 0 ran 1000 steps without waiting for input".to_string()
+            })
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::VecDeque;
+
+use crate::computer::{IntcodeComputer, OperationType, StepResult};
+use crate::error::IntcodeError;
+
+// Undo records kept by default, oldest dropped first.
+const HISTORY_LIMIT: usize = 1 << 20;
+
+// What one executed instruction changed, enough to undo it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Change {
+    pub pointer: i64,
+    pub relative_base: i64,
+    // written address and the value it held before
+    pub write: Option<(i64, i64)>,
+    // input value the instruction consumed
+    pub input: Option<i64>,
+    pub output: bool,
+}
+
+// Undo log of executed instructions for stepping an IntcodeComputer back.
+#[derive(Debug, Clone)]
+pub struct History {
+    changes: VecDeque<Change>,
+    limit: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History::new()
+    }
+}
+
+impl History {
+    pub fn new() -> Self {
+        History::with_limit(HISTORY_LIMIT)
+    }
+
+    pub fn with_limit(limit: usize) -> Self {
+        History {
+            changes: VecDeque::new(),
+            limit,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    // most recent change
+    pub fn last(&self) -> Option<&Change> {
+        self.changes.back()
+    }
+
+    pub fn clear(&mut self) {
+        self.changes.clear();
+    }
+
+    // Step `computer` and record how to undo the step.
+    pub fn step(
+        &mut self,
+        computer: &mut IntcodeComputer,
+        input: &mut VecDeque<i64>,
+    ) -> Result<StepResult, IntcodeError> {
+        if computer.halted {
+            return Ok(StepResult::Halted);
+        }
+        let reads_input: bool = computer.parse_instruction()?.operation == OperationType::CPY;
+        let mut change = Change {
+            pointer: computer.pointer,
+            relative_base: computer.relative_base,
+            write: computer
+                .write_address()?
+                .map(|address| (address, computer.program.read(address as usize))),
+            input: if reads_input {
+                input.front().copied()
+            } else {
+                None
+            },
+            output: false,
+        };
+
+        let result: StepResult = computer.step(input)?;
+        match result {
+            StepResult::NeedsInput => return Ok(result),
+            StepResult::Output(_) => change.output = true,
+            StepResult::Executed | StepResult::Halted => {}
+        }
+
+        if self.changes.len() == self.limit {
+            self.changes.pop_front();
+        }
+        if self.limit > 0 {
+            self.changes.push_back(change);
+        }
+        Ok(result)
+    }
+
+    // Undo the last recorded instruction. Consumed input goes back to the
+    // front of `input`. Returns None once the log is exhausted.
+    pub fn step_back(
+        &mut self,
+        computer: &mut IntcodeComputer,
+        input: &mut VecDeque<i64>,
+    ) -> Option<Change> {
+        let change: Change = self.changes.pop_back()?;
+        if let Some((address, value)) = change.write {
+            computer.program.write(address as usize, value);
+        }
+        if let Some(value) = change.input {
+            input.push_front(value);
+        }
+        computer.pointer = change.pointer;
+        computer.relative_base = change.relative_base;
+        computer.halted = false;
+        Some(change)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assemble, Memory};
+
+    #[test]
+    fn stepping_back_restores_every_register_and_cell() {
+        let values: Vec<i64> = assemble(
+            "
+                    rbo #30
+                    in [rb+0]
+            loop:   out [rb+0]
+                    mul [rb+0], #2, [rb+0]
+                    lt [rb+0], #40, [29]
+                    jnz [29], #loop
+                    hlt
+            ",
+        )
+        .unwrap();
+        let mut computer = IntcodeComputer::new(Memory::new(values.clone()));
+        let mut input: VecDeque<i64> = VecDeque::from([5]);
+        let mut history = History::new();
+
+        let mut output: Vec<i64> = Vec::new();
+        while !computer.halted {
+            if let StepResult::Output(value) = history.step(&mut computer, &mut input).unwrap() {
+                output.push(value);
+            }
+        }
+        assert_eq!(output, vec![5, 10, 20]);
+        assert_eq!(history.len(), 15);
+
+        let mut outputs: usize = 0;
+        while let Some(change) = history.step_back(&mut computer, &mut input) {
+            outputs += change.output as usize;
+        }
+        assert_eq!(outputs, 3);
+        assert_eq!(input, VecDeque::from([5]));
+        assert_eq!((computer.pointer, computer.relative_base), (0, 0));
+        assert!(!computer.halted);
+        for (address, value) in values.iter().enumerate() {
+            assert_eq!(computer.program.read(address), *value);
+        }
+        assert_eq!(computer.program.read(30), 0);
+    }
+
+    #[test]
+    fn limit_drops_the_oldest_changes() {
+        let mut computer = IntcodeComputer::new(Memory::new(
+            assemble("rbo #1\nrbo #2\nrbo #3\nhlt").unwrap(),
+        ));
+        let mut history = History::with_limit(2);
+        for _ in 0..3 {
+            history.step(&mut computer, &mut VecDeque::new()).unwrap();
+        }
+        assert_eq!(history.len(), 2);
+        history.step_back(&mut computer, &mut VecDeque::new());
+        history.step_back(&mut computer, &mut VecDeque::new());
+        assert_eq!((computer.pointer, computer.relative_base), (2, 1));
+        assert_eq!(history.step_back(&mut computer, &mut VecDeque::new()), None);
+    }
+}
@@ -0,0 +1,850 @@
+use std::collections::VecDeque;
+use std::mem::offset_of;
+
+use cranelift_codegen::ir::condcodes::IntCC;
+use cranelift_codegen::ir::{types, AbiParam, Block, FuncRef, InstBuilder, MemFlags, Value};
+use cranelift_codegen::settings::{self, Configurable};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, FuncId, Linkage, Module};
+
+use crate::computer::{
+    parse_instruction, IntcodeComputer, Operation, OperationType, ParameterMode, StepResult,
+};
+use crate::device::{drive, InputDevice, OutputDevice};
+use crate::error::IntcodeError;
+use crate::memory::Memory;
+
+// Longest run of instructions compiled into one block.
+const MAX_BLOCK_INSTRUCTIONS: usize = 256;
+
+// What a compiled block returns.
+const EXIT_NEXT: i64 = 0;
+// it wrote `State::written`, a cell some compiled block was built from
+const EXIT_WROTE_CODE: i64 = 1;
+// it touched the negative address `State::fault`
+const EXIT_FAULT: i64 = 2;
+
+// Machine state as compiled code sees it.
+#[repr(C)]
+struct State {
+    dense: *mut i64,
+    dense_len: i64,
+    // per cell, how many compiled blocks were built from it
+    code: *const u32,
+    code_len: i64,
+    pointer: i64,
+    relative_base: i64,
+    written: i64,
+    fault: i64,
+    faulted: i64,
+    memory: *mut Memory,
+}
+
+type BlockFn = unsafe extern "C" fn(*mut State) -> i64;
+
+// Reads that miss the dense cells.
+extern "C" fn read_slow(state: *mut State, address: i64) -> i64 {
+    // SAFETY: only called by compiled code with the state `execute` built.
+    let state: &mut State = unsafe { &mut *state };
+    if address < 0 {
+        state.faulted = 1;
+        state.fault = address;
+        return 0;
+    }
+    unsafe { (*state.memory).read(address as usize) }
+}
+
+// Writes that miss the dense cells. Writing may grow them.
+extern "C" fn write_slow(state: *mut State, address: i64, value: i64) {
+    // SAFETY: only called by compiled code with the state `execute` built.
+    let state: &mut State = unsafe { &mut *state };
+    if address < 0 {
+        state.faulted = 1;
+        state.fault = address;
+        return;
+    }
+    let memory: &mut Memory = unsafe { &mut *state.memory };
+    memory.write(address as usize, value);
+    let dense: &mut [i64] = memory.dense_mut();
+    state.dense = dense.as_mut_ptr();
+    state.dense_len = dense.len() as i64;
+}
+
+fn jit_error(error: impl std::fmt::Display) -> IntcodeError {
+    IntcodeError::Jit {
+        message: error.to_string(),
+    }
+}
+
+fn field(offset: usize) -> i32 {
+    offset as i32
+}
+
+// Builds the body of one block.
+struct Codegen<'a> {
+    builder: FunctionBuilder<'a>,
+    state: Value,
+    relative_base: Variable,
+    read_slow: FuncRef,
+    write_slow: FuncRef,
+    faults: Vec<(Block, i64)>,
+    // side exits, filled in by `emit_exits`
+    pending_exits: Vec<(Block, i64)>,
+}
+
+impl Codegen<'_> {
+    fn load_state(&mut self, offset: usize) -> Value {
+        self.builder
+            .ins()
+            .load(types::I64, MemFlags::trusted(), self.state, field(offset))
+    }
+
+    fn store_state(&mut self, offset: usize, value: Value) {
+        self.builder
+            .ins()
+            .store(MemFlags::trusted(), value, self.state, field(offset));
+    }
+
+    // Leave the block at `pointer`.
+    fn exit(&mut self, pointer: Value, code: i64) {
+        let relative_base: Value = self.builder.use_var(self.relative_base);
+        self.store_state(offset_of!(State, pointer), pointer);
+        self.store_state(offset_of!(State, relative_base), relative_base);
+        let code: Value = self.builder.ins().iconst(types::I64, code);
+        self.builder.ins().return_(&[code]);
+    }
+
+    // Block that reports a fault in the instruction at `pointer`, filled in
+    // by `emit_faults` once the rest of the code is done.
+    fn fault_block(&mut self, pointer: i64) -> Block {
+        let fault: Block = self.builder.create_block();
+        self.faults.push((fault, pointer));
+        fault
+    }
+
+    fn emit_faults(&mut self) {
+        for (fault, pointer) in std::mem::take(&mut self.faults) {
+            self.builder.switch_to_block(fault);
+            let pointer: Value = self.builder.ins().iconst(types::I64, pointer);
+            self.exit(pointer, EXIT_FAULT);
+        }
+    }
+
+    fn cell_address(&mut self, address: Value) -> Value {
+        let dense: Value = self.load_state(offset_of!(State, dense));
+        let offset: Value = self.builder.ins().ishl_imm(address, 3);
+        self.builder.ins().iadd(dense, offset)
+    }
+
+    fn read(&mut self, address: Value, pointer: i64) -> Value {
+        let fast: Block = self.builder.create_block();
+        let slow: Block = self.builder.create_block();
+        let join: Block = self.builder.create_block();
+        self.builder.append_block_param(join, types::I64);
+        let fault: Block = self.fault_block(pointer);
+
+        let dense_len: Value = self.load_state(offset_of!(State, dense_len));
+        let dense: Value = self
+            .builder
+            .ins()
+            .icmp(IntCC::UnsignedLessThan, address, dense_len);
+        self.builder.ins().brif(dense, fast, &[], slow, &[]);
+
+        self.builder.switch_to_block(fast);
+        let cell: Value = self.cell_address(address);
+        let value: Value = self
+            .builder
+            .ins()
+            .load(types::I64, MemFlags::trusted(), cell, 0);
+        self.builder.ins().jump(join, &[value]);
+
+        self.builder.switch_to_block(slow);
+        let call = self
+            .builder
+            .ins()
+            .call(self.read_slow, &[self.state, address]);
+        let value: Value = self.builder.inst_results(call)[0];
+        let faulted: Value = self.load_state(offset_of!(State, faulted));
+        self.builder.ins().brif(faulted, fault, &[], join, &[value]);
+
+        self.builder.switch_to_block(join);
+        self.builder.block_params(join)[0]
+    }
+
+    fn write(&mut self, address: Value, value: Value, pointer: i64, next: i64) {
+        let fast: Block = self.builder.create_block();
+        let slow: Block = self.builder.create_block();
+        let check: Block = self.builder.create_block();
+        let wrote_code: Block = self.builder.create_block();
+        let done: Block = self.builder.create_block();
+        let fault: Block = self.fault_block(pointer);
+
+        let dense_len: Value = self.load_state(offset_of!(State, dense_len));
+        let dense: Value = self
+            .builder
+            .ins()
+            .icmp(IntCC::UnsignedLessThan, address, dense_len);
+        self.builder.ins().brif(dense, fast, &[], slow, &[]);
+
+        self.builder.switch_to_block(fast);
+        let cell: Value = self.cell_address(address);
+        self.builder
+            .ins()
+            .store(MemFlags::trusted(), value, cell, 0);
+        let code_len: Value = self.load_state(offset_of!(State, code_len));
+        let covered: Value = self
+            .builder
+            .ins()
+            .icmp(IntCC::UnsignedLessThan, address, code_len);
+        self.builder.ins().brif(covered, check, &[], done, &[]);
+
+        self.builder.switch_to_block(check);
+        let code: Value = self.load_state(offset_of!(State, code));
+        let offset: Value = self.builder.ins().ishl_imm(address, 2);
+        let count_address: Value = self.builder.ins().iadd(code, offset);
+        let count: Value = self
+            .builder
+            .ins()
+            .uload32(MemFlags::trusted(), count_address, 0);
+        self.builder.ins().brif(count, wrote_code, &[], done, &[]);
+
+        self.builder.switch_to_block(wrote_code);
+        self.store_state(offset_of!(State, written), address);
+        let next: Value = self.builder.ins().iconst(types::I64, next);
+        self.exit(next, EXIT_WROTE_CODE);
+
+        // cells past the dense ones are never code
+        self.builder.switch_to_block(slow);
+        self.builder
+            .ins()
+            .call(self.write_slow, &[self.state, address, value]);
+        let faulted: Value = self.load_state(offset_of!(State, faulted));
+        self.builder.ins().brif(faulted, fault, &[], done, &[]);
+
+        self.builder.switch_to_block(done);
+    }
+
+    fn operand(&mut self, instruction: &Decoded, parameter: usize) -> Value {
+        let value: i64 = instruction.parameters[parameter - 1];
+        let constant: Value = self.builder.ins().iconst(types::I64, value);
+        match instruction.operation.parameter_mode(parameter) {
+            ParameterMode::ImmediateMode => constant,
+            ParameterMode::PositionMode => self.read(constant, instruction.pointer),
+            ParameterMode::RelativeMode => {
+                let relative_base: Value = self.builder.use_var(self.relative_base);
+                let address: Value = self.builder.ins().iadd(relative_base, constant);
+                self.read(address, instruction.pointer)
+            }
+        }
+    }
+
+    fn target(&mut self, instruction: &Decoded, parameter: usize) -> Value {
+        let constant: Value = self
+            .builder
+            .ins()
+            .iconst(types::I64, instruction.parameters[parameter - 1]);
+        match instruction.operation.parameter_mode(parameter) {
+            ParameterMode::RelativeMode => {
+                let relative_base: Value = self.builder.use_var(self.relative_base);
+                self.builder.ins().iadd(relative_base, constant)
+            }
+            _ => constant,
+        }
+    }
+
+    // Exit to `pointer` when `condition` holds, else carry on in a new block.
+    fn side_exit(&mut self, condition: Value, pointer: i64, loop_body: Option<Block>) {
+        let carry_on: Block = self.builder.create_block();
+        let taken: Block = match loop_body {
+            Some(body) => body,
+            None => {
+                let taken: Block = self.builder.create_block();
+                self.pending_exits.push((taken, pointer));
+                taken
+            }
+        };
+        self.builder
+            .ins()
+            .brif(condition, taken, &[], carry_on, &[]);
+        self.builder.switch_to_block(carry_on);
+    }
+
+    fn emit_exits(&mut self) {
+        for (exit, pointer) in std::mem::take(&mut self.pending_exits) {
+            self.builder.switch_to_block(exit);
+            let pointer: Value = self.builder.ins().iconst(types::I64, pointer);
+            self.exit(pointer, EXIT_NEXT);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Decoded {
+    pointer: i64,
+    operation: Operation,
+    parameters: Vec<i64>,
+}
+
+impl Decoded {
+    fn next(&self) -> i64 {
+        self.pointer + 1 + self.parameters.len() as i64
+    }
+
+    // Whether a jump is always or never taken, when its condition is an
+    // immediate.
+    fn constant_condition(&self) -> Option<bool> {
+        if self.operation.first_parameter_mode != ParameterMode::ImmediateMode {
+            return None;
+        }
+        let nonzero: bool = self.parameters[0] != 0;
+        Some(match self.operation.operation {
+            OperationType::JIT => nonzero,
+            _ => !nonzero,
+        })
+    }
+
+    fn immediate_target(&self) -> Option<i64> {
+        (self.operation.second_parameter_mode == ParameterMode::ImmediateMode)
+            .then_some(self.parameters[1])
+    }
+}
+
+struct CompiledBlock {
+    function: BlockFn,
+    // cells the block was built from, as half-open ranges
+    cells: Vec<(i64, i64)>,
+}
+
+// `IntcodeComputer` that compiles the code it runs to native code with
+// Cranelift. A block starts where execution enters it and runs along the
+// fall-through path of conditional jumps and through unconditional ones,
+// leaving them by side exits; loops back to its start stay inside it. Blocks
+// stop before in, out and hlt, which the interpreter executes, and are
+// thrown away when the program writes into the cells they were built from.
+pub struct JitComputer {
+    computer: IntcodeComputer,
+    module: Option<JITModule>,
+    read_slow: FuncId,
+    write_slow: FuncId,
+    builder_context: FunctionBuilderContext,
+    // indexed by start address
+    blocks: Vec<Option<CompiledBlock>>,
+    live: Vec<i64>,
+    code: Vec<u32>,
+    compiled: usize,
+    invalidated: usize,
+}
+
+impl JitComputer {
+    pub fn new(program: Memory) -> Result<Self, IntcodeError> {
+        let mut flags = settings::builder();
+        flags.set("opt_level", "speed").map_err(jit_error)?;
+        flags.set("enable_verifier", "false").map_err(jit_error)?;
+        let isa = cranelift_native::builder()
+            .map_err(jit_error)?
+            .finish(settings::Flags::new(flags))
+            .map_err(jit_error)?;
+        let mut builder = JITBuilder::with_isa(isa, default_libcall_names());
+        builder.symbol("intcode_read_slow", read_slow as *const u8);
+        builder.symbol("intcode_write_slow", write_slow as *const u8);
+        let mut module = JITModule::new(builder);
+
+        let pointer_type = module.target_config().pointer_type();
+        let mut signature = module.make_signature();
+        signature.params.push(AbiParam::new(pointer_type));
+        signature.params.push(AbiParam::new(types::I64));
+        signature.returns.push(AbiParam::new(types::I64));
+        let read_slow = module
+            .declare_function("intcode_read_slow", Linkage::Import, &signature)
+            .map_err(jit_error)?;
+        signature.params.push(AbiParam::new(types::I64));
+        signature.returns.clear();
+        let write_slow = module
+            .declare_function("intcode_write_slow", Linkage::Import, &signature)
+            .map_err(jit_error)?;
+
+        Ok(JitComputer {
+            computer: IntcodeComputer::new(program),
+            module: Some(module),
+            read_slow,
+            write_slow,
+            builder_context: FunctionBuilderContext::new(),
+            blocks: Vec::new(),
+            live: Vec::new(),
+            code: Vec::new(),
+            compiled: 0,
+            invalidated: 0,
+        })
+    }
+
+    pub fn computer(&self) -> &IntcodeComputer {
+        &self.computer
+    }
+
+    // Blocks compiled so far, including ones thrown away since.
+    pub fn compiled_blocks(&self) -> usize {
+        self.compiled
+    }
+
+    pub fn invalidated_blocks(&self) -> usize {
+        self.invalidated
+    }
+
+    fn decode(&self, pointer: i64, dense_len: i64) -> Option<Decoded> {
+        let word: i64 = self.computer.program.read(pointer as usize);
+        let operation: Operation = parse_instruction(pointer, word).ok()?;
+        let size: i64 = 1 + operation.operation.parameters() as i64;
+        let writes: bool = match operation.operation {
+            OperationType::SUM | OperationType::MUL | OperationType::LTH | OperationType::EQL => {
+                true
+            }
+            OperationType::JIT | OperationType::JIF | OperationType::ARB => false,
+            // left to the interpreter
+            OperationType::CPY | OperationType::OUT | OperationType::END => return None,
+        };
+        if writes && operation.third_parameter_mode == ParameterMode::ImmediateMode {
+            return None;
+        }
+        if pointer.saturating_add(size) > dense_len {
+            return None;
+        }
+        let parameters: Vec<i64> = (1..size)
+            .map(|offset| self.computer.program.read((pointer + offset) as usize))
+            .collect();
+        Some(Decoded {
+            pointer,
+            operation,
+            parameters,
+        })
+    }
+
+    // Instructions of the block starting at `start`, in the order they run.
+    fn decode_block(&mut self, start: i64) -> Vec<Decoded> {
+        let dense_len: i64 = self.computer.program.dense_mut().len() as i64;
+        let mut instructions: Vec<Decoded> = Vec::new();
+        let mut pointer: i64 = start;
+        while instructions.len() < MAX_BLOCK_INSTRUCTIONS
+            && pointer >= 0
+            && instructions.iter().all(|seen| seen.pointer != pointer)
+        {
+            let Some(instruction) = self.decode(pointer, dense_len) else {
+                break;
+            };
+            let next: Option<i64> = match instruction.operation.operation {
+                OperationType::JIT | OperationType::JIF => {
+                    match (
+                        instruction.constant_condition(),
+                        instruction.immediate_target(),
+                    ) {
+                        (Some(false), _) => Some(instruction.next()),
+                        (Some(true), Some(target)) if target != start => Some(target),
+                        (None, Some(_)) => Some(instruction.next()),
+                        _ => None,
+                    }
+                }
+                _ => Some(instruction.next()),
+            };
+            instructions.push(instruction);
+            match next {
+                Some(next) => pointer = next,
+                None => break,
+            }
+        }
+        instructions
+    }
+
+    fn compile(&mut self, start: i64) -> Result<Option<BlockFn>, IntcodeError> {
+        let instructions: Vec<Decoded> = self.decode_block(start);
+        if instructions.is_empty() {
+            return Ok(None);
+        }
+        let module: &mut JITModule = self.module.as_mut().unwrap();
+
+        let mut context = module.make_context();
+        let pointer_type = module.target_config().pointer_type();
+        context
+            .func
+            .signature
+            .params
+            .push(AbiParam::new(pointer_type));
+        context
+            .func
+            .signature
+            .returns
+            .push(AbiParam::new(types::I64));
+        let id: FuncId = module
+            .declare_anonymous_function(&context.func.signature)
+            .map_err(jit_error)?;
+
+        let mut builder = FunctionBuilder::new(&mut context.func, &mut self.builder_context);
+        let read_slow: FuncRef = module.declare_func_in_func(self.read_slow, builder.func);
+        let write_slow: FuncRef = module.declare_func_in_func(self.write_slow, builder.func);
+        let entry: Block = builder.create_block();
+        let body: Block = builder.create_block();
+        builder.append_block_params_for_function_params(entry);
+        builder.switch_to_block(entry);
+        let state: Value = builder.block_params(entry)[0];
+        let relative_base = Variable::from_u32(0);
+        builder.declare_var(relative_base, types::I64);
+
+        let mut codegen = Codegen {
+            builder,
+            state,
+            relative_base,
+            read_slow,
+            write_slow,
+            faults: Vec::new(),
+            pending_exits: Vec::new(),
+        };
+        let initial: Value = codegen.load_state(offset_of!(State, relative_base));
+        codegen.builder.def_var(relative_base, initial);
+        codegen.builder.ins().jump(body, &[]);
+        codegen.builder.switch_to_block(body);
+
+        let mut ended: bool = false;
+        for (index, instruction) in instructions.iter().enumerate() {
+            let (pointer, next): (i64, i64) = (instruction.pointer, instruction.next());
+            let following: Option<i64> = instructions.get(index + 1).map(|next| next.pointer);
+            match instruction.operation.operation {
+                OperationType::SUM
+                | OperationType::MUL
+                | OperationType::LTH
+                | OperationType::EQL => {
+                    let a: Value = codegen.operand(instruction, 1);
+                    let b: Value = codegen.operand(instruction, 2);
+                    let ins = codegen.builder.ins();
+                    let value: Value = match instruction.operation.operation {
+                        OperationType::SUM => ins.iadd(a, b),
+                        OperationType::MUL => ins.imul(a, b),
+                        OperationType::LTH => {
+                            let flag: Value = ins.icmp(IntCC::SignedLessThan, a, b);
+                            codegen.builder.ins().uextend(types::I64, flag)
+                        }
+                        _ => {
+                            let flag: Value = ins.icmp(IntCC::Equal, a, b);
+                            codegen.builder.ins().uextend(types::I64, flag)
+                        }
+                    };
+                    let address: Value = codegen.target(instruction, 3);
+                    codegen.write(address, value, pointer, next);
+                }
+                OperationType::ARB => {
+                    let offset: Value = codegen.operand(instruction, 1);
+                    let relative_base: Value = codegen.builder.use_var(relative_base);
+                    let relative_base: Value = codegen.builder.ins().iadd(relative_base, offset);
+                    codegen
+                        .builder
+                        .def_var(codegen.relative_base, relative_base);
+                }
+                OperationType::JIT | OperationType::JIF => {
+                    let value: Value = codegen.operand(instruction, 1);
+                    let target: Value = codegen.operand(instruction, 2);
+                    let immediate_target: Option<i64> = instruction.immediate_target();
+                    let to_start: Option<Block> = (immediate_target == Some(start)).then_some(body);
+                    let taken: Value = match instruction.operation.operation {
+                        OperationType::JIT => value,
+                        _ => codegen.builder.ins().icmp_imm(IntCC::Equal, value, 0),
+                    };
+                    match (instruction.constant_condition(), following) {
+                        (Some(false), Some(following)) if following == next => {}
+                        (Some(true), Some(following)) if Some(following) == immediate_target => {}
+                        (None, Some(following)) if following == next => {
+                            codegen.side_exit(taken, immediate_target.unwrap(), to_start);
+                        }
+                        // the block ends here
+                        (Some(true), _) if to_start.is_some() => {
+                            codegen.builder.ins().jump(body, &[]);
+                            ended = true;
+                        }
+                        (None, _) if to_start.is_some() => {
+                            codegen.side_exit(taken, start, to_start);
+                            let next: Value = codegen.builder.ins().iconst(types::I64, next);
+                            codegen.exit(next, EXIT_NEXT);
+                            ended = true;
+                        }
+                        _ => {
+                            let next: Value = codegen.builder.ins().iconst(types::I64, next);
+                            let pointer: Value = codegen.builder.ins().select(taken, target, next);
+                            codegen.exit(pointer, EXIT_NEXT);
+                            ended = true;
+                        }
+                    }
+                }
+                OperationType::CPY | OperationType::OUT | OperationType::END => unreachable!(),
+            }
+        }
+        if !ended {
+            let last: &Decoded = instructions.last().unwrap();
+            let next: Value = codegen.builder.ins().iconst(types::I64, last.next());
+            codegen.exit(next, EXIT_NEXT);
+        }
+        codegen.emit_exits();
+        codegen.emit_faults();
+        codegen.builder.seal_all_blocks();
+        codegen.builder.finalize();
+
+        module
+            .define_function(id, &mut context)
+            .map_err(jit_error)?;
+        module.clear_context(&mut context);
+        module.finalize_definitions().map_err(jit_error)?;
+        // SAFETY: the function was just defined with the BlockFn signature.
+        let function: BlockFn = unsafe { std::mem::transmute(module.get_finalized_function(id)) };
+
+        let cells: Vec<(i64, i64)> = instructions
+            .iter()
+            .map(|instruction| (instruction.pointer, instruction.next()))
+            .collect();
+        let end: usize = cells.iter().map(|(_, end)| *end as usize).max().unwrap();
+        if self.code.len() < end {
+            self.code.resize(end, 0);
+        }
+        for (from, to) in &cells {
+            for count in &mut self.code[*from as usize..*to as usize] {
+                *count += 1;
+            }
+        }
+        if self.blocks.len() <= start as usize {
+            self.blocks.resize_with(start as usize + 1, || None);
+        }
+        self.blocks[start as usize] = Some(CompiledBlock { function, cells });
+        self.live.push(start);
+        self.compiled += 1;
+        Ok(Some(function))
+    }
+
+    // Forget every block built from cell `address`.
+    fn invalidate(&mut self, address: i64) {
+        let blocks: &mut Vec<Option<CompiledBlock>> = &mut self.blocks;
+        let code: &mut Vec<u32> = &mut self.code;
+        let mut invalidated: usize = 0;
+        self.live.retain(|start| {
+            let block: &CompiledBlock = blocks[*start as usize].as_ref().unwrap();
+            if !block
+                .cells
+                .iter()
+                .any(|(from, to)| (*from..*to).contains(&address))
+            {
+                return true;
+            }
+            for (from, to) in &block.cells {
+                for count in &mut code[*from as usize..*to as usize] {
+                    *count -= 1;
+                }
+            }
+            blocks[*start as usize] = None;
+            invalidated += 1;
+            false
+        });
+        self.invalidated += invalidated;
+    }
+
+    fn execute(&mut self, function: BlockFn) -> Result<(), IntcodeError> {
+        let memory: *mut Memory = &mut self.computer.program;
+        // SAFETY: `memory` stays valid for the call, and nothing else
+        // touches the computer while the block runs.
+        let dense: &mut [i64] = unsafe { (*memory).dense_mut() };
+        let mut state = State {
+            dense: dense.as_mut_ptr(),
+            dense_len: dense.len() as i64,
+            code: self.code.as_ptr(),
+            code_len: self.code.len() as i64,
+            pointer: self.computer.pointer,
+            relative_base: self.computer.relative_base,
+            written: 0,
+            fault: 0,
+            faulted: 0,
+            memory,
+        };
+        let exit: i64 = unsafe { function(&mut state) };
+        self.computer.pointer = state.pointer;
+        self.computer.relative_base = state.relative_base;
+        match exit {
+            EXIT_WROTE_CODE => self.invalidate(state.written),
+            EXIT_FAULT => {
+                return Err(IntcodeError::NegativeAddress {
+                    pointer: state.pointer,
+                    address: state.fault,
+                })
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // Same as `IntcodeComputer::step`, except that a whole compiled block
+    // counts as one step.
+    pub fn step(&mut self, input: &mut VecDeque<i64>) -> Result<StepResult, IntcodeError> {
+        if self.computer.halted {
+            return Ok(StepResult::Halted);
+        }
+        let pointer: i64 = self.computer.pointer;
+        let compiled: Option<BlockFn> = self
+            .blocks
+            .get(pointer.max(0) as usize)
+            .and_then(|block| block.as_ref())
+            .map(|block| block.function);
+        let function: Option<BlockFn> = match compiled {
+            Some(function) => Some(function),
+            None if pointer >= 0 => self.compile(pointer)?,
+            None => None,
+        };
+        if let Some(function) = function {
+            self.execute(function)?;
+            return Ok(StepResult::Executed);
+        }
+
+        let written: Option<i64> = self.computer.write_address().unwrap_or(None);
+        let result: StepResult = self.computer.step(input)?;
+        if let Some(address) = written.filter(|_| result == StepResult::Executed) {
+            if address >= 0
+                && (address as usize) < self.code.len()
+                && self.code[address as usize] > 0
+            {
+                self.invalidate(address);
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn run_until_output(
+        &mut self,
+        input: &mut VecDeque<i64>,
+    ) -> Result<Option<i64>, IntcodeError> {
+        loop {
+            match self.step(input)? {
+                StepResult::Executed => continue,
+                StepResult::Output(value) => return Ok(Some(value)),
+                StepResult::NeedsInput | StepResult::Halted => return Ok(None),
+            }
+        }
+    }
+
+    pub fn run_until_input(&mut self, input: &mut VecDeque<i64>) -> Result<Vec<i64>, IntcodeError> {
+        let mut output: Vec<i64> = Vec::new();
+        loop {
+            match self.step(input)? {
+                StepResult::Executed => continue,
+                StepResult::Output(value) => output.push(value),
+                StepResult::NeedsInput | StepResult::Halted => return Ok(output),
+            }
+        }
+    }
+
+    pub fn run(&mut self, input: &mut VecDeque<i64>) -> Result<Vec<i64>, IntcodeError> {
+        self.run_until_input(input)
+    }
+
+    // Same as `IntcodeComputer::run_devices`.
+    pub fn run_devices<I, O>(
+        &mut self,
+        input: &mut I,
+        output: &mut O,
+    ) -> Result<StepResult, IntcodeError>
+    where
+        I: InputDevice + ?Sized,
+        O: OutputDevice + ?Sized,
+    {
+        drive(|queue| self.step(queue), input, output)
+    }
+}
+
+impl Drop for JitComputer {
+    fn drop(&mut self) {
+        self.blocks.clear();
+        if let Some(module) = self.module.take() {
+            // SAFETY: no compiled function outlives the blocks cleared above.
+            unsafe { module.free_memory() };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assemble, parse};
+
+    fn both(values: Vec<i64>, input: &[i64]) -> (Vec<i64>, JitComputer) {
+        let mut interpreted = IntcodeComputer::new(Memory::new(values.clone()));
+        let expected: Vec<i64> = interpreted
+            .run(&mut input.iter().copied().collect())
+            .unwrap();
+        let mut jit = JitComputer::new(Memory::new(values)).unwrap();
+        let output: Vec<i64> = jit.run(&mut input.iter().copied().collect()).unwrap();
+        assert_eq!(output, expected);
+        assert_eq!(jit.computer().pointer, interpreted.pointer);
+        assert_eq!(jit.computer().relative_base, interpreted.relative_base);
+        (output, jit)
+    }
+
+    #[test]
+    fn runs_day09_like_the_interpreter() {
+        let filename: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../day09/input.txt");
+        let program: Memory = parse(filename).unwrap();
+        let values: Vec<i64> = program.runs().remove(0).1;
+        assert_eq!(both(values.clone(), &[1]).0, vec![4234906522]);
+        let (output, jit) = both(values, &[2]);
+        assert_eq!(output, vec![60962]);
+        assert!(jit.compiled_blocks() > 0);
+        assert!(jit.computer().halted);
+    }
+
+    #[test]
+    fn recompiles_code_the_program_rewrites() {
+        // counts down from the input, patching the immediate it adds each
+        // time round so the running total grows by 0, 1, 2, ...
+        let values: Vec<i64> = assemble(
+            "
+                    in [count]
+            loop:   add [total], #0, [total]
+                    add [loop+2], #1, [loop+2]
+                    add [count], #-1, [count]
+                    jnz [count], #loop
+                    out [total]
+                    hlt
+            count:  .data 0
+            total:  .data 0
+            ",
+        )
+        .unwrap();
+        let (output, jit) = both(values, &[10]);
+        assert_eq!(output, vec![45]);
+        assert!(jit.invalidated_blocks() >= 10);
+    }
+
+    #[test]
+    fn wraps_like_the_interpreter() {
+        // 3 to the power of the input, well past i64::MAX
+        let values: Vec<i64> = assemble(
+            "
+                    in [count]
+            loop:   mul [value], #3, [value]
+                    add [value], #0, [value]
+                    add [count], #-1, [count]
+                    jnz [count], #loop
+                    out [value]
+                    hlt
+            count:  .data 0
+            value:  .data 1
+            ",
+        )
+        .unwrap();
+        let (output, _) = both(values, &[100]);
+        assert_eq!(output, vec![3i64.wrapping_pow(100)]);
+    }
+
+    #[test]
+    fn reports_negative_addresses() {
+        let values: Vec<i64> = assemble("rbo #-5\nadd [rb+1], #1, [0]\nhlt").unwrap();
+        let mut interpreted = IntcodeComputer::new(Memory::new(values.clone()));
+        let mut jit = JitComputer::new(Memory::new(values)).unwrap();
+        let error = Some(IntcodeError::NegativeAddress {
+            pointer: 2,
+            address: -4,
+        });
+        assert_eq!(interpreted.run(&mut VecDeque::new()).err(), error);
+        assert_eq!(jit.run(&mut VecDeque::new()).err(), error);
+        assert_eq!(jit.computer().pointer, 2);
+    }
+}
@@ -0,0 +1,58 @@
+// Shared Intcode computer used by every Intcode day since day09.
+
+mod analysis;
+mod ascii;
+mod assembler;
+mod computer;
+mod debugger;
+mod decompiler;
+mod device;
+mod disassembler;
+mod error;
+mod executor;
+mod history;
+#[cfg(feature = "jit")]
+mod jit;
+mod memory;
+mod network;
+mod profile;
+mod session;
+mod snapshot;
+mod springscript;
+mod trace;
+mod translate;
+
+pub use analysis::{
+    analyse, Block, ControlFlowGraph, Edge, EdgeKind, SelfModifyingWrite, Subroutine,
+};
+pub use ascii::AsciiComputer;
+pub use assembler::assemble;
+pub use computer::{
+    parse, parse_instruction, parse_program, parse_values, IntcodeComputer, Operation,
+    OperationType, ParameterMode, StepResult,
+};
+pub use debugger::{Debugger, Stop};
+pub use decompiler::decompile;
+pub use device::{
+    AsciiInput, AsciiOutput, InputDevice, InputFn, NumberInput, NumberOutput, OutputDevice,
+    OutputFn,
+};
+pub use disassembler::{
+    decode, disassemble, encode, find_code, format_operand, listing, Instruction, Line,
+};
+pub use error::IntcodeError;
+pub use executor::{ExecutionLog, ThreadedNetwork};
+pub use history::{Change, History};
+#[cfg(feature = "jit")]
+pub use jit::JitComputer;
+pub use memory::Memory;
+pub use network::{FirstPacket, Nat, NatAction, Network, Packet, WakeOnIdle, NAT_ADDRESS};
+pub use profile::{BasicBlock, HotLoop, IoGap, Profiler};
+pub use session::{load_transcript, Session};
+pub use snapshot::Snapshot;
+pub use springscript::{
+    failed_hull, solve, synthesize, Gate, Mode, Register, SpringInstruction, Springscript,
+    MAX_INSTRUCTIONS,
+};
+pub use trace::{read_binary_trace, TraceEvent, TraceFilter, TraceFormat, Tracer};
+pub use translate::{translate, Translated, TranslatedFn};
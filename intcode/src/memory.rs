@@ -0,0 +1,149 @@
+use std::cmp;
+use std::collections::HashMap;
+
+// Dense memory grows in whole pages; addresses at or past `dense_limit` go
+// to the sparse overflow map instead.
+const PAGE_SIZE: usize = 1024;
+pub(crate) const DENSE_LIMIT: usize = 1 << 20;
+
+#[derive(Debug, Clone)]
+pub struct Memory {
+    dense: Vec<i64>,
+    sparse: HashMap<usize, i64>,
+    dense_limit: usize,
+}
+
+impl Memory {
+    pub fn new(values: Vec<i64>) -> Self {
+        Memory::with_dense_limit(values, DENSE_LIMIT)
+    }
+
+    // A `dense_limit` of 0 keeps every cell in the sparse map, which is how
+    // memory worked before it was paged.
+    pub fn with_dense_limit(values: Vec<i64>, dense_limit: usize) -> Self {
+        let mut memory = Memory {
+            dense: Vec::new(),
+            sparse: HashMap::new(),
+            dense_limit,
+        };
+        for (address, value) in values.into_iter().enumerate() {
+            memory.write(address, value);
+        }
+        memory
+    }
+
+    pub fn read(&self, address: usize) -> i64 {
+        if address < self.dense.len() {
+            self.dense[address]
+        } else if address < self.dense_limit {
+            0
+        } else {
+            *self.sparse.get(&address).unwrap_or(&0)
+        }
+    }
+
+    pub fn write(&mut self, address: usize, value: i64) {
+        if address >= self.dense_limit {
+            self.sparse.insert(address, value);
+            return;
+        }
+        if address >= self.dense.len() {
+            let pages: usize = address / PAGE_SIZE + 1;
+            self.dense
+                .resize(cmp::min(pages * PAGE_SIZE, self.dense_limit), 0);
+        }
+        self.dense[address] = value;
+    }
+
+    pub fn dense_limit(&self) -> usize {
+        self.dense_limit
+    }
+
+    // The dense cells, for code that addresses them directly.
+    #[cfg(feature = "jit")]
+    pub(crate) fn dense_mut(&mut self) -> &mut [i64] {
+        &mut self.dense
+    }
+
+    // Contiguous runs of cells covering every non-zero cell, in address
+    // order: the dense part without its trailing zeros, then each sparse cell.
+    pub fn runs(&self) -> Vec<(usize, Vec<i64>)> {
+        let mut runs: Vec<(usize, Vec<i64>)> = Vec::new();
+        let used: usize = self
+            .dense
+            .iter()
+            .rposition(|value| *value != 0)
+            .map_or(0, |last| last + 1);
+        if used > 0 {
+            runs.push((0, self.dense[..used].to_vec()));
+        }
+
+        let mut sparse: Vec<(usize, i64)> = self
+            .sparse
+            .iter()
+            .filter(|(_, value)| **value != 0)
+            .map(|(address, value)| (*address, *value))
+            .collect();
+        sparse.sort();
+        runs.extend(
+            sparse
+                .into_iter()
+                .map(|(address, value)| (address, vec![value])),
+        );
+        runs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unwritten_cells_read_as_zero() {
+        let memory = Memory::new(vec![1, 2, 3]);
+        assert_eq!(memory.read(2), 3);
+        assert_eq!(memory.read(3), 0);
+        assert_eq!(memory.read(DENSE_LIMIT * 4), 0);
+    }
+
+    #[test]
+    fn dense_memory_grows_by_pages() {
+        let mut memory = Memory::new(vec![1, 2, 3]);
+        assert_eq!(memory.dense.len(), PAGE_SIZE);
+
+        memory.write(PAGE_SIZE + 5, 7);
+        assert_eq!(memory.dense.len(), 2 * PAGE_SIZE);
+        assert_eq!(memory.read(PAGE_SIZE + 5), 7);
+    }
+
+    #[test]
+    fn high_addresses_overflow_into_sparse_memory() {
+        let mut memory = Memory::new(vec![]);
+        memory.write(DENSE_LIMIT + 10, 5);
+
+        assert!(memory.dense.is_empty());
+        assert_eq!(memory.read(DENSE_LIMIT + 10), 5);
+    }
+
+    #[test]
+    fn runs_cover_non_zero_cells() {
+        let mut memory = Memory::new(vec![1, 0, 2, 0]);
+        memory.write(DENSE_LIMIT + 3, 4);
+        memory.write(DENSE_LIMIT + 1, 3);
+        assert_eq!(
+            memory.runs(),
+            vec![
+                (0, vec![1, 0, 2]),
+                (DENSE_LIMIT + 1, vec![3]),
+                (DENSE_LIMIT + 3, vec![4])
+            ]
+        );
+    }
+
+    #[test]
+    fn zero_dense_limit_is_fully_sparse() {
+        let memory = Memory::with_dense_limit(vec![4, 5], 0);
+        assert!(memory.dense.is_empty());
+        assert_eq!(memory.read(1), 5);
+    }
+}
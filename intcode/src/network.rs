@@ -0,0 +1,264 @@
+use std::collections::VecDeque;
+
+use crate::computer::IntcodeComputer;
+use crate::error::IntcodeError;
+use crate::memory::Memory;
+
+// Address packets for the NAT are sent to.
+pub const NAT_ADDRESS: i64 = 255;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Packet {
+    pub address: i64,
+    pub x: i64,
+    pub y: i64,
+}
+
+// What the network does after the NAT has seen a packet or an idle network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NatAction {
+    Continue,
+    Send(Packet),
+    Stop(i64),
+}
+
+// Policy for the packets sent to NAT_ADDRESS and for waking the network up
+// once it goes idle.
+pub trait Nat {
+    fn receive(&mut self, packet: Packet) -> NatAction;
+    fn idle(&mut self) -> NatAction;
+}
+
+// Stops with the Y value of the first packet the NAT gets.
+#[derive(Debug, Clone, Default)]
+pub struct FirstPacket;
+
+impl Nat for FirstPacket {
+    fn receive(&mut self, packet: Packet) -> NatAction {
+        NatAction::Stop(packet.y)
+    }
+
+    fn idle(&mut self) -> NatAction {
+        NatAction::Continue
+    }
+}
+
+// Keeps the last packet it got and sends it to address 0 whenever the
+// network is idle. Stops with the first Y value it sends twice in a row.
+#[derive(Debug, Clone, Default)]
+pub struct WakeOnIdle {
+    last: Option<Packet>,
+    last_sent_y: Option<i64>,
+}
+
+impl Nat for WakeOnIdle {
+    fn receive(&mut self, packet: Packet) -> NatAction {
+        self.last = Some(packet);
+        NatAction::Continue
+    }
+
+    fn idle(&mut self) -> NatAction {
+        let packet: Packet = match self.last {
+            Some(packet) => packet,
+            None => return NatAction::Continue,
+        };
+        if self.last_sent_y == Some(packet.y) {
+            return NatAction::Stop(packet.y);
+        }
+        self.last_sent_y = Some(packet.y);
+        NatAction::Send(Packet {
+            address: 0,
+            ..packet
+        })
+    }
+}
+
+// A computer on the network. Its input queue holds the X and Y values of
+// the packets sent to it, and its output the start of the next packet it
+// sends.
+#[derive(Debug, Clone)]
+struct Node {
+    computer: IntcodeComputer,
+    queue: VecDeque<i64>,
+    sending: Vec<i64>,
+}
+
+impl Node {
+    // Run until the computer asks for input with nothing left to read. A
+    // NIC with no packets waiting reads -1.
+    fn run(&mut self) -> Result<Vec<Packet>, IntcodeError> {
+        if self.computer.halted {
+            return Ok(Vec::new());
+        }
+        if self.queue.is_empty() {
+            self.queue.push_back(-1);
+        }
+        self.sending
+            .extend(self.computer.run_until_input(&mut self.queue)?);
+        let complete: usize = self.sending.len() / 3 * 3;
+        Ok(self
+            .sending
+            .drain(..complete)
+            .collect::<Vec<i64>>()
+            .chunks(3)
+            .map(|values| Packet {
+                address: values[0],
+                x: values[1],
+                y: values[2],
+            })
+            .collect())
+    }
+}
+
+// Computers at addresses 0 up to the number of nodes, each booted with its
+// own address, run in turn until blocked on input. The network is idle
+// once a whole round starts with every queue empty and sends nothing: each
+// node read -1 and went back to waiting.
+#[derive(Debug, Clone)]
+pub struct Network {
+    nodes: Vec<Node>,
+    rounds: usize,
+}
+
+impl Network {
+    pub fn new(program: Memory, size: usize) -> Self {
+        let nodes: Vec<Node> = (0..size)
+            .map(|address| Node {
+                computer: IntcodeComputer::new(program.clone()),
+                queue: VecDeque::from([address as i64]),
+                sending: Vec::new(),
+            })
+            .collect();
+        Network { nodes, rounds: 0 }
+    }
+
+    pub fn computer(&self, address: usize) -> &IntcodeComputer {
+        &self.nodes[address].computer
+    }
+
+    // rounds run so far
+    pub fn rounds(&self) -> usize {
+        self.rounds
+    }
+
+    // nothing waiting to be read or sent, apart from at halted nodes
+    pub fn is_idle(&self) -> bool {
+        self.nodes
+            .iter()
+            .all(|node| node.computer.halted || (node.queue.is_empty() && node.sending.is_empty()))
+    }
+
+    pub fn send(&mut self, packet: Packet) -> Result<(), IntcodeError> {
+        let node: &mut Node = usize::try_from(packet.address)
+            .ok()
+            .and_then(|address| self.nodes.get_mut(address))
+            .ok_or(IntcodeError::UnknownAddress {
+                address: packet.address,
+            })?;
+        node.queue.extend([packet.x, packet.y]);
+        Ok(())
+    }
+
+    // Run every node once, until it next blocks on input. Packets are
+    // delivered as soon as they are sent, so later nodes see them in the
+    // same round. Returns the packets sent to the NAT.
+    pub fn round(&mut self) -> Result<Vec<Packet>, IntcodeError> {
+        self.rounds += 1;
+        let mut for_nat: Vec<Packet> = Vec::new();
+        for address in 0..self.nodes.len() {
+            for packet in self.nodes[address].run()? {
+                match packet.address {
+                    NAT_ADDRESS => for_nat.push(packet),
+                    _ => self.send(packet)?,
+                }
+            }
+        }
+        Ok(for_nat)
+    }
+
+    // Run rounds until the NAT says to stop, and return its value.
+    pub fn run(&mut self, nat: &mut impl Nat) -> Result<i64, IntcodeError> {
+        loop {
+            let was_idle: bool = self.is_idle();
+            let for_nat: Vec<Packet> = self.round()?;
+            let mut action: NatAction = NatAction::Continue;
+            for packet in &for_nat {
+                action = nat.receive(*packet);
+                if action != NatAction::Continue {
+                    break;
+                }
+            }
+            if was_idle && for_nat.is_empty() && self.is_idle() {
+                action = nat.idle();
+                if action == NatAction::Continue {
+                    return Err(IntcodeError::NetworkIdle);
+                }
+            }
+            match action {
+                NatAction::Continue => {}
+                NatAction::Send(packet) => self.send(packet)?,
+                NatAction::Stop(value) => return Ok(value),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assemble;
+
+    // node 0 sends (7, 8) to node 1, and every node passes whatever it
+    // gets on to the NAT
+    fn relay() -> Memory {
+        let values: Vec<i64> = assemble(
+            "
+                    in [100]
+                    jnz [100], #listen
+                    out #1
+                    out #7
+                    out #8
+            listen: in [101]
+                    eq [101], #-1, [102]
+                    jnz [102], #listen
+                    in [103]
+                    out #255
+                    out [101]
+                    out [103]
+                    jnz #1, #listen
+            ",
+        )
+        .unwrap();
+        Memory::new(values)
+    }
+
+    #[test]
+    fn routes_packets_to_the_nat() {
+        let mut network = Network::new(relay(), 3);
+        assert_eq!(network.run(&mut FirstPacket), Ok(8));
+        assert_eq!(network.rounds(), 1);
+    }
+
+    #[test]
+    fn wakes_the_network_when_idle() {
+        let mut network = Network::new(relay(), 3);
+        assert_eq!(network.run(&mut WakeOnIdle::default()), Ok(8));
+
+        // nothing is ever sent, so there is nothing to wake it with
+        let silent: Memory = Memory::new(assemble("loop: in [100]\njnz #1, #loop").unwrap());
+        assert_eq!(
+            Network::new(silent, 2).run(&mut WakeOnIdle::default()),
+            Err(IntcodeError::NetworkIdle)
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_addresses() {
+        let values: Vec<i64> = assemble("out #9\nout #1\nout #2\nhlt").unwrap();
+        let mut network = Network::new(Memory::new(values), 2);
+        assert_eq!(
+            network.round(),
+            Err(IntcodeError::UnknownAddress { address: 9 })
+        );
+    }
+}
@@ -0,0 +1,373 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+use crate::computer::{IntcodeComputer, OperationType, StepResult};
+use crate::error::IntcodeError;
+
+// Rows shown per section of the report.
+const REPORT_ROWS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Site {
+    operation: OperationType,
+    size: i64,
+    count: u64,
+}
+
+// Straight-line run of instructions that always execute together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub start: i64,
+    // address of the last instruction in the block
+    pub end: i64,
+    pub instructions: usize,
+    pub executions: u64,
+}
+
+// Backward jump from `end` to `start`, taken `iterations` times.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HotLoop {
+    pub start: i64,
+    pub end: i64,
+    pub iterations: u64,
+    // instructions executed inside start..=end
+    pub instructions: u64,
+}
+
+// Work done between two consecutive I/O events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoGap {
+    pub instructions: u64,
+    pub elapsed: Duration,
+}
+
+// Counts executions per address and opcode while running a computer, and
+// keeps enough of the control flow to recover basic blocks and loops.
+#[derive(Debug, Clone)]
+pub struct Profiler {
+    sites: HashMap<i64, Site>,
+    opcodes: HashMap<OperationType, u64>,
+    // taken jumps (from, to) that did not fall through to the next instruction
+    jumps: HashMap<(i64, i64), u64>,
+    io_gaps: Vec<IoGap>,
+    instructions: u64,
+    since_io: u64,
+    last_io: Instant,
+    started: Instant,
+    vm_time: Duration,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Profiler::new()
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Profiler {
+            sites: HashMap::new(),
+            opcodes: HashMap::new(),
+            jumps: HashMap::new(),
+            io_gaps: Vec::new(),
+            instructions: 0,
+            since_io: 0,
+            last_io: Instant::now(),
+            started: Instant::now(),
+            vm_time: Duration::ZERO,
+        }
+    }
+
+    fn io_event(&mut self) {
+        let now: Instant = Instant::now();
+        self.io_gaps.push(IoGap {
+            instructions: self.since_io,
+            elapsed: now - self.last_io,
+        });
+        self.since_io = 0;
+        self.last_io = now;
+    }
+
+    // `IntcodeComputer::step` that counts the instruction it executed.
+    pub fn step(
+        &mut self,
+        computer: &mut IntcodeComputer,
+        input: &mut VecDeque<i64>,
+    ) -> Result<StepResult, IntcodeError> {
+        if computer.halted {
+            return Ok(StepResult::Halted);
+        }
+        let pointer: i64 = computer.pointer;
+        let operation: OperationType = computer.parse_instruction()?.operation;
+        let result: StepResult = computer.step(input)?;
+        if result == StepResult::NeedsInput {
+            return Ok(result);
+        }
+
+        let size: i64 = 1 + operation.parameters() as i64;
+        let site: &mut Site = self.sites.entry(pointer).or_insert(Site {
+            operation,
+            size,
+            count: 0,
+        });
+        site.operation = operation;
+        site.size = size;
+        site.count += 1;
+        *self.opcodes.entry(operation).or_insert(0) += 1;
+        if computer.pointer != pointer.wrapping_add(size) && !computer.halted {
+            *self.jumps.entry((pointer, computer.pointer)).or_insert(0) += 1;
+        }
+
+        self.instructions += 1;
+        self.since_io += 1;
+        if operation == OperationType::CPY || operation == OperationType::OUT {
+            self.io_event();
+        }
+        Ok(result)
+    }
+
+    // Profiled `IntcodeComputer::run`: runs until input is missing or halt.
+    pub fn run(
+        &mut self,
+        computer: &mut IntcodeComputer,
+        input: &mut VecDeque<i64>,
+    ) -> Result<Vec<i64>, IntcodeError> {
+        let start: Instant = Instant::now();
+        let mut output: Vec<i64> = Vec::new();
+        loop {
+            match self.step(computer, input)? {
+                StepResult::Output(value) => output.push(value),
+                StepResult::Executed => {}
+                StepResult::NeedsInput | StepResult::Halted => break,
+            }
+        }
+        self.vm_time += start.elapsed();
+        Ok(output)
+    }
+
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    pub fn address_count(&self, address: i64) -> u64 {
+        self.sites.get(&address).map_or(0, |site| site.count)
+    }
+
+    pub fn opcode_count(&self, operation: OperationType) -> u64 {
+        *self.opcodes.get(&operation).unwrap_or(&0)
+    }
+
+    pub fn io_gaps(&self) -> &[IoGap] {
+        &self.io_gaps
+    }
+
+    // Blocks over the executed code, most executed instructions first. A block
+    // starts at a jump target or after a jump and ends at a jump, a halt or
+    // before the next block.
+    pub fn basic_blocks(&self) -> Vec<BasicBlock> {
+        let sites: BTreeMap<i64, Site> = self.sites.iter().map(|(a, s)| (*a, *s)).collect();
+        let targets: HashSet<i64> = self.jumps.keys().map(|(_, to)| *to).collect();
+        let ends_block = |site: &Site| {
+            matches!(
+                site.operation,
+                OperationType::JIT | OperationType::JIF | OperationType::END
+            )
+        };
+
+        let mut blocks: Vec<BasicBlock> = Vec::new();
+        let mut current: Option<BasicBlock> = None;
+        let mut next: i64 = i64::MIN;
+        for (address, site) in &sites {
+            let joins: bool = *address == next && !targets.contains(address);
+            match current.as_mut() {
+                Some(block) if joins => {
+                    block.end = *address;
+                    block.instructions += 1;
+                    block.executions = block.executions.max(site.count);
+                }
+                _ => {
+                    blocks.extend(current.take());
+                    current = Some(BasicBlock {
+                        start: *address,
+                        end: *address,
+                        instructions: 1,
+                        executions: site.count,
+                    });
+                }
+            }
+            next = address + site.size;
+            if ends_block(site) {
+                blocks.extend(current.take());
+                next = i64::MIN;
+            }
+        }
+        blocks.extend(current);
+
+        blocks.sort_by_key(|block| std::cmp::Reverse(block.executions * block.instructions as u64));
+        blocks
+    }
+
+    // Backward jumps, hottest first.
+    pub fn hot_loops(&self) -> Vec<HotLoop> {
+        let mut loops: Vec<HotLoop> = self
+            .jumps
+            .iter()
+            .filter(|((from, to), _)| to <= from)
+            .map(|((from, to), iterations)| HotLoop {
+                start: *to,
+                end: *from,
+                iterations: *iterations,
+                instructions: self
+                    .sites
+                    .iter()
+                    .filter(|(address, _)| (*to..=*from).contains(*address))
+                    .map(|(_, site)| site.count)
+                    .sum(),
+            })
+            .collect();
+        loops.sort_by_key(|hot_loop| {
+            (
+                std::cmp::Reverse(hot_loop.instructions),
+                hot_loop.start,
+                hot_loop.end,
+            )
+        });
+        loops
+    }
+
+    pub fn report(&self) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        let wall: Duration = self.started.elapsed();
+        let rate: f64 = self.instructions as f64 / self.vm_time.as_secs_f64().max(1e-9);
+        lines.push(format!(
+            "{} instructions, {:?} in the VM ({:.1}M/s), {:?} outside it",
+            self.instructions,
+            self.vm_time,
+            rate / 1e6,
+            wall.saturating_sub(self.vm_time)
+        ));
+
+        lines.push("\nopcodes:".to_string());
+        let mut opcodes: Vec<(&OperationType, &u64)> = self.opcodes.iter().collect();
+        opcodes.sort_by_key(|(_, count)| std::cmp::Reverse(**count));
+        for (operation, count) in opcodes {
+            lines.push(format!(
+                "  {:<4} {:>12} {:>6.1}%",
+                operation.mnemonic(),
+                count,
+                100.0 * *count as f64 / self.instructions.max(1) as f64
+            ));
+        }
+
+        lines.push("\nhot addresses:".to_string());
+        let mut sites: Vec<(&i64, &Site)> = self.sites.iter().collect();
+        sites.sort_by_key(|(address, site)| (std::cmp::Reverse(site.count), **address));
+        for (address, site) in sites.into_iter().take(REPORT_ROWS) {
+            lines.push(format!(
+                "  {:>6} {:<4} {:>12}",
+                address,
+                site.operation.mnemonic(),
+                site.count
+            ));
+        }
+
+        lines.push("\nbasic blocks:".to_string());
+        for block in self.basic_blocks().into_iter().take(REPORT_ROWS) {
+            lines.push(format!(
+                "  {:>6}..{:<6} {:>3} instructions {:>12} executions",
+                block.start, block.end, block.instructions, block.executions
+            ));
+        }
+
+        lines.push("\nhot loops:".to_string());
+        for hot_loop in self.hot_loops().into_iter().take(REPORT_ROWS) {
+            lines.push(format!(
+                "  {:>6}..{:<6} {:>12} iterations {:>12} instructions",
+                hot_loop.start, hot_loop.end, hot_loop.iterations, hot_loop.instructions
+            ));
+        }
+
+        if !self.io_gaps.is_empty() {
+            let count: u64 = self.io_gaps.len() as u64;
+            let instructions: u64 = self.io_gaps.iter().map(|gap| gap.instructions).sum();
+            let elapsed: Duration = self.io_gaps.iter().map(|gap| gap.elapsed).sum();
+            let longest: &IoGap = self.io_gaps.iter().max_by_key(|gap| gap.elapsed).unwrap();
+            lines.push(format!(
+                "\nI/O: {} events, {} instructions and {:?} between them on average, longest gap {:?} ({} instructions)",
+                count,
+                instructions / count,
+                elapsed
+                    .checked_div(u32::try_from(count).unwrap_or(u32::MAX))
+                    .unwrap_or_default(),
+                longest.elapsed,
+                longest.instructions
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assemble, Memory};
+
+    // sums 1..=n, with an inner loop that runs twice per outer iteration
+    const NESTED: &str = "
+                in [100]
+        outer:  add [101], [100], [101]
+                add #0, #2, [102]
+        inner:  add [102], #-1, [102]
+                jnz [102], #inner
+                add [100], #-1, [100]
+                jnz [100], #outer
+                out [101]
+                hlt
+    ";
+
+    fn profile(n: i64) -> Profiler {
+        let values: Vec<i64> = assemble(NESTED).unwrap();
+        let mut computer = IntcodeComputer::new(Memory::new(values));
+        let mut profiler = Profiler::new();
+        let output: Vec<i64> = profiler
+            .run(&mut computer, &mut VecDeque::from([n]))
+            .unwrap();
+        assert_eq!(output, vec![n * (n + 1) / 2]);
+        profiler
+    }
+
+    #[test]
+    fn counts_addresses_and_opcodes() {
+        let profiler = profile(5);
+        assert_eq!(profiler.address_count(0), 1);
+        assert_eq!(profiler.address_count(2), 5);
+        assert_eq!(profiler.address_count(10), 10);
+        assert_eq!(profiler.opcode_count(OperationType::JIT), 15);
+        assert_eq!(profiler.instructions(), 1 + 5 * 8 + 2);
+        assert_eq!(profiler.io_gaps().len(), 2);
+        assert_eq!(profiler.io_gaps()[1].instructions, 5 * 8 + 1);
+    }
+
+    #[test]
+    fn recovers_blocks_and_loops() {
+        let profiler = profile(5);
+        let blocks: Vec<(i64, i64)> = profiler
+            .basic_blocks()
+            .iter()
+            .map(|block| (block.start, block.end))
+            .collect();
+        assert_eq!(blocks, vec![(10, 14), (2, 6), (17, 21), (24, 26), (0, 0)]);
+
+        let loops: Vec<HotLoop> = profiler.hot_loops();
+        assert_eq!(loops.len(), 2);
+        assert_eq!(
+            (loops[0].start, loops[0].end, loops[0].iterations),
+            (2, 21, 4)
+        );
+        assert_eq!(
+            (loops[1].start, loops[1].end, loops[1].iterations),
+            (10, 14, 5)
+        );
+        assert!(profiler.report().contains("hot loops:"));
+    }
+}
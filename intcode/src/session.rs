@@ -0,0 +1,132 @@
+use std::fs;
+
+use crate::ascii::AsciiComputer;
+use crate::error::IntcodeError;
+use crate::memory::Memory;
+
+// An ASCII program being played by hand. Every line sent to it is kept, so
+// the transcript can be saved and replayed later, or pasted into a solution
+// as its list of commands.
+#[derive(Debug, Clone)]
+pub struct Session {
+    computer: AsciiComputer,
+    transcript: Vec<String>,
+}
+
+impl Session {
+    pub fn new(program: Memory) -> Self {
+        Session {
+            computer: AsciiComputer::new(program),
+            transcript: Vec::new(),
+        }
+    }
+
+    pub fn computer(&self) -> &AsciiComputer {
+        &self.computer
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.computer.is_halted()
+    }
+
+    pub fn transcript(&self) -> &[String] {
+        &self.transcript
+    }
+
+    // Text printed before the program first asks for input.
+    pub fn start(&mut self) -> Result<String, IntcodeError> {
+        self.computer.read_until_prompt()
+    }
+
+    // Send `line` and return the program's reply.
+    pub fn command(&mut self, line: &str) -> Result<String, IntcodeError> {
+        self.computer.send_line(line)?;
+        self.transcript.push(line.to_string());
+        self.computer.read_until_prompt()
+    }
+
+    // Send every line of `commands` in turn, stopping early if the program
+    // halts. Returns the replies, each after its command.
+    pub fn replay(&mut self, commands: &[String]) -> Result<String, IntcodeError> {
+        let mut text: String = String::new();
+        for line in commands {
+            if self.is_halted() {
+                break;
+            }
+            text.push_str(&format!("> {}\n", line));
+            text.push_str(&self.command(line)?);
+        }
+        Ok(text)
+    }
+
+    pub fn save_transcript(&self, filename: &str) -> Result<(), IntcodeError> {
+        let mut data: String = String::new();
+        for line in &self.transcript {
+            data.push_str(line);
+            data.push('\n');
+        }
+        fs::write(filename, data).map_err(|_| IntcodeError::FileNotWritable {
+            filename: filename.to_string(),
+        })
+    }
+}
+
+// Commands saved by `Session::save_transcript`, one per line.
+pub fn load_transcript(filename: &str) -> Result<Vec<String>, IntcodeError> {
+    let data: String =
+        fs::read_to_string(filename).map_err(|error| IntcodeError::reading(filename, error))?;
+    Ok(data.lines().map(|line| line.to_string()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assemble;
+
+    // answers every line with "ok", until it reads an empty one
+    fn echo() -> Memory {
+        let values: Vec<i64> = assemble(
+            "
+            loop:   in [100]
+                    eq [100], #10, [101]
+                    jnz [101], #done
+            skip:   in [100]
+                    eq [100], #10, [101]
+                    jz [101], #skip
+                    out #111
+                    out #107
+                    out #10
+                    jnz #1, #loop
+            done:   hlt
+            ",
+        )
+        .unwrap();
+        Memory::new(values)
+    }
+
+    #[test]
+    fn saves_and_replays_the_transcript() {
+        let mut session = Session::new(echo());
+        assert_eq!(session.start().unwrap(), "");
+        assert_eq!(session.command("north").unwrap(), "ok\n");
+        assert_eq!(session.command("take cake").unwrap(), "ok\n");
+
+        let filename =
+            std::env::temp_dir().join(format!("intcode-{}.transcript", std::process::id()));
+        let filename: &str = filename.to_str().unwrap();
+        session.save_transcript(filename).unwrap();
+        let mut commands: Vec<String> = load_transcript(filename).unwrap();
+        fs::remove_file(filename).unwrap();
+        assert_eq!(commands, vec!["north", "take cake"]);
+
+        commands.extend(["".to_string(), "west".to_string()]);
+        let mut replayed = Session::new(echo());
+        replayed.start().unwrap();
+        assert_eq!(
+            replayed.replay(&commands).unwrap(),
+            "> north\nok\n> take cake\nok\n> \n"
+        );
+        assert!(replayed.is_halted());
+        assert_eq!(replayed.transcript().len(), 3);
+    }
+}
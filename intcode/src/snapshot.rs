@@ -0,0 +1,267 @@
+use std::collections::VecDeque;
+use std::fs;
+
+use crate::computer::IntcodeComputer;
+use crate::error::IntcodeError;
+use crate::memory::{Memory, DENSE_LIMIT};
+
+// Text format, one field per line:
+//
+//     intcode-snapshot 2
+//     pointer 25
+//     relative_base 1000
+//     halted false
+//     input 1,2
+//     output 72,105
+//     dense_limit 1048576
+//     memory 0 1102,34463338,34463338,63
+//     memory 1048600 7
+//
+// Every `memory` line holds consecutive cells from the given address; cells
+// not listed are zero. Bump FORMAT_VERSION whenever the layout changes.
+const MAGIC: &str = "intcode-snapshot";
+const FORMAT_VERSION: u32 = 2;
+
+// A computer together with the I/O it has not dealt with yet: input queued
+// but not read and output produced but not consumed.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub computer: IntcodeComputer,
+    pub input: VecDeque<i64>,
+    pub output: Vec<i64>,
+}
+
+fn join(values: &[i64]) -> String {
+    let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+    values.join(",")
+}
+
+fn invalid(line: usize, message: String) -> IntcodeError {
+    IntcodeError::InvalidSnapshot { line, message }
+}
+
+fn parse_number(line: usize, text: &str) -> Result<i64, IntcodeError> {
+    text.trim()
+        .parse()
+        .map_err(|_| invalid(line, format!("invalid number {:?}", text)))
+}
+
+fn parse_list(line: usize, text: &str) -> Result<Vec<i64>, IntcodeError> {
+    if text.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    text.split(',')
+        .map(|value| parse_number(line, value))
+        .collect()
+}
+
+impl Snapshot {
+    pub fn new(computer: IntcodeComputer) -> Self {
+        Snapshot {
+            computer,
+            input: VecDeque::new(),
+            output: Vec::new(),
+        }
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut data: String = format!("{} {}\n", MAGIC, FORMAT_VERSION);
+        data.push_str(&format!("pointer {}\n", self.computer.pointer));
+        data.push_str(&format!("relative_base {}\n", self.computer.relative_base));
+        data.push_str(&format!("halted {}\n", self.computer.halted));
+        data.push_str(&format!(
+            "input {}\n",
+            join(&self.input.iter().copied().collect::<Vec<i64>>())
+        ));
+        data.push_str(&format!("output {}\n", join(&self.output)));
+        data.push_str(&format!(
+            "dense_limit {}\n",
+            self.computer.program.dense_limit()
+        ));
+        for (address, values) in self.computer.program.runs() {
+            data.push_str(&format!("memory {} {}\n", address, join(&values)));
+        }
+        data
+    }
+
+    pub fn deserialize(data: &str) -> Result<Self, IntcodeError> {
+        let mut lines = data
+            .lines()
+            .enumerate()
+            .map(|(index, text)| (index + 1, text));
+
+        match lines.next().and_then(|(_, header)| header.split_once(' ')) {
+            Some((MAGIC, version)) if version.trim() == FORMAT_VERSION.to_string() => {}
+            Some((MAGIC, version)) => {
+                return Err(invalid(
+                    1,
+                    format!("unsupported version {}", version.trim()),
+                ))
+            }
+            _ => return Err(invalid(1, "not an Intcode snapshot".to_string())),
+        }
+
+        let mut snapshot = Snapshot::new(IntcodeComputer::new(Memory::new(Vec::new())));
+        // memory is filled in once the dense limit it was saved with is known
+        let mut dense_limit: Option<usize> = None;
+        let mut cells: Vec<(usize, i64)> = Vec::new();
+        for (line, text) in lines {
+            let (key, value) = text.split_once(' ').unwrap_or((text, ""));
+            match key {
+                "pointer" => snapshot.computer.pointer = parse_number(line, value)?,
+                "relative_base" => snapshot.computer.relative_base = parse_number(line, value)?,
+                "halted" => {
+                    snapshot.computer.halted = value
+                        .trim()
+                        .parse()
+                        .map_err(|_| invalid(line, format!("invalid flag {:?}", value)))?
+                }
+                "input" => snapshot.input = parse_list(line, value)?.into(),
+                "output" => snapshot.output = parse_list(line, value)?,
+                "dense_limit" => {
+                    // anything bigger could have a tiny file allocate
+                    // gigabytes of dense memory
+                    let limit: i64 = parse_number(line, value)?;
+                    dense_limit = Some(
+                        usize::try_from(limit)
+                            .ok()
+                            .filter(|limit| *limit <= DENSE_LIMIT)
+                            .ok_or_else(|| {
+                                invalid(line, format!("invalid dense limit {}", limit))
+                            })?,
+                    );
+                }
+                "memory" => {
+                    let (address, values) = value
+                        .split_once(' ')
+                        .ok_or_else(|| invalid(line, "memory line without values".to_string()))?;
+                    let address: i64 = parse_number(line, address)?;
+                    if address < 0 {
+                        return Err(invalid(line, format!("negative address {}", address)));
+                    }
+                    for (offset, value) in parse_list(line, values)?.into_iter().enumerate() {
+                        let cell: usize =
+                            (address as usize).checked_add(offset).ok_or_else(|| {
+                                invalid(line, format!("memory from {} runs past the end", address))
+                            })?;
+                        cells.push((cell, value));
+                    }
+                }
+                "" => {}
+                _ => return Err(invalid(line, format!("unknown field {:?}", key))),
+            }
+        }
+        if let Some(dense_limit) = dense_limit {
+            snapshot.computer.program = Memory::with_dense_limit(Vec::new(), dense_limit);
+        }
+        for (cell, value) in cells {
+            snapshot.computer.program.write(cell, value);
+        }
+        Ok(snapshot)
+    }
+
+    pub fn save(&self, filename: &str) -> Result<(), IntcodeError> {
+        fs::write(filename, self.serialize()).map_err(|_| IntcodeError::FileNotWritable {
+            filename: filename.to_string(),
+        })
+    }
+
+    pub fn load(filename: &str) -> Result<Self, IntcodeError> {
+        let data =
+            fs::read_to_string(filename).map_err(|error| IntcodeError::reading(filename, error))?;
+        Snapshot::deserialize(&data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assemble, parse, StepResult};
+
+    #[test]
+    fn round_trip_keeps_state_and_pending_io() {
+        let filename: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../day09/input.txt");
+        let mut computer = IntcodeComputer::new(parse(filename).unwrap());
+        let mut input: VecDeque<i64> = VecDeque::from([1]);
+        computer.run_until_input(&mut input).unwrap();
+        computer.program.write(1 << 30, -5);
+
+        let snapshot = Snapshot {
+            computer,
+            input: VecDeque::from([7, 8]),
+            output: vec![4234906522],
+        };
+        let restored = Snapshot::deserialize(&snapshot.serialize()).unwrap();
+        assert_eq!(restored.serialize(), snapshot.serialize());
+        assert_eq!(restored.computer.pointer, snapshot.computer.pointer);
+        assert_eq!(
+            restored.computer.relative_base,
+            snapshot.computer.relative_base
+        );
+        assert!(restored.computer.halted);
+        assert_eq!(restored.input, VecDeque::from([7, 8]));
+        assert_eq!(restored.computer.program.read(1 << 30), -5);
+    }
+
+    #[test]
+    fn keeps_the_dense_limit() {
+        let memory = Memory::with_dense_limit(vec![1, 2, 3], 0);
+        let snapshot = Snapshot::new(IntcodeComputer::new(memory));
+        let restored = Snapshot::deserialize(&snapshot.serialize()).unwrap();
+        assert_eq!(restored.computer.program.dense_limit(), 0);
+        assert_eq!(restored.computer.program.read(2), 3);
+    }
+
+    #[test]
+    fn restored_machine_carries_on_where_it_stopped() {
+        let values: Vec<i64> = assemble("in [9]\nout [9]\nin [9]\nout [9]\nhlt").unwrap();
+        let mut computer = IntcodeComputer::new(Memory::new(values));
+        let mut input: VecDeque<i64> = VecDeque::from([3]);
+        assert_eq!(computer.run(&mut input), Ok(vec![3]));
+
+        let filename =
+            std::env::temp_dir().join(format!("intcode-{}.snapshot", std::process::id()));
+        let filename: &str = filename.to_str().unwrap();
+        Snapshot::new(computer.fork()).save(filename).unwrap();
+        let mut restored = Snapshot::load(filename).unwrap();
+        fs::remove_file(filename).unwrap();
+
+        assert_eq!(
+            restored.computer.step(&mut VecDeque::new()),
+            Ok(StepResult::NeedsInput)
+        );
+        assert_eq!(restored.computer.run(&mut VecDeque::from([5])), Ok(vec![5]));
+        assert_eq!(computer.run(&mut VecDeque::from([6])), Ok(vec![6]));
+    }
+
+    #[test]
+    fn rejects_unknown_versions_and_fields() {
+        assert_eq!(
+            Snapshot::deserialize("intcode-snapshot 3\n").err(),
+            Some(IntcodeError::InvalidSnapshot {
+                line: 1,
+                message: "unsupported version 3".to_string()
+            })
+        );
+        assert!(Snapshot::deserialize("1,2,3").is_err());
+        assert!(Snapshot::deserialize("intcode-snapshot 2\npointer x\n").is_err());
+        assert!(Snapshot::deserialize("intcode-snapshot 2\nregisters 1\n").is_err());
+
+        // a huge dense limit would have the memory line allocate ~1e12 cells
+        let huge: String = format!(
+            "intcode-snapshot 2\ndense_limit {}\nmemory 1000000000000 1\n",
+            DENSE_LIMIT + 1
+        );
+        assert_eq!(
+            Snapshot::deserialize(&huge).err(),
+            Some(IntcodeError::InvalidSnapshot {
+                line: 2,
+                message: format!("invalid dense limit {}", DENSE_LIMIT + 1)
+            })
+        );
+        assert!(
+            Snapshot::deserialize("intcode-snapshot 2\ndense_limit 18446744073709551615\n")
+                .is_err()
+        );
+    }
+}
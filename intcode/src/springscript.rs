@@ -0,0 +1,567 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::ascii::AsciiComputer;
+use crate::error::IntcodeError;
+use crate::memory::Memory;
+
+// Most instructions a springdroid can remember, not counting WALK or RUN.
+pub const MAX_INSTRUCTIONS: usize = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Walk,
+    Run,
+}
+
+impl Mode {
+    // number of sensors, A to D when walking and A to I when running
+    pub fn sensors(&self) -> usize {
+        match self {
+            Mode::Walk => 4,
+            Mode::Run => 9,
+        }
+    }
+
+    pub fn command(&self) -> &'static str {
+        match self {
+            Mode::Walk => "WALK",
+            Mode::Run => "RUN",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Register {
+    // ground sensor, 0 for A, 1 for B, ...
+    Sensor(usize),
+    T,
+    J,
+}
+
+impl Register {
+    fn parse(name: &str) -> Option<Register> {
+        match name {
+            "T" => Some(Register::T),
+            "J" => Some(Register::J),
+            _ if name.len() == 1 && ("A"..="I").contains(&name) => {
+                Some(Register::Sensor((name.as_bytes()[0] - b'A') as usize))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Register::Sensor(sensor) => write!(f, "{}", (b'A' + *sensor as u8) as char),
+            Register::T => write!(f, "T"),
+            Register::J => write!(f, "J"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Gate {
+    And,
+    Or,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpringInstruction {
+    pub gate: Gate,
+    pub x: Register,
+    // always T or J
+    pub y: Register,
+}
+
+impl fmt::Display for SpringInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let gate: &str = match self.gate {
+            Gate::And => "AND",
+            Gate::Or => "OR",
+            Gate::Not => "NOT",
+        };
+        write!(f, "{} {} {}", gate, self.x, self.y)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Springscript {
+    pub instructions: Vec<SpringInstruction>,
+    pub mode: Mode,
+}
+
+fn script_error(line: usize, message: String) -> IntcodeError {
+    IntcodeError::Springscript { line, message }
+}
+
+impl Springscript {
+    // One instruction per line, ending with WALK or RUN. Blank lines are
+    // skipped.
+    pub fn parse(text: &str) -> Result<Self, IntcodeError> {
+        let mut instructions: Vec<SpringInstruction> = Vec::new();
+        let mut mode: Option<Mode> = None;
+
+        for (index, line) in text.lines().enumerate() {
+            let line_number: usize = index + 1;
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            if tokens.is_empty() {
+                continue;
+            }
+            if mode.is_some() {
+                return Err(script_error(
+                    line_number,
+                    format!("{:?} after the end of the script", line.trim()),
+                ));
+            }
+            let gate: Gate = match tokens[0] {
+                "WALK" | "RUN" if tokens.len() == 1 => {
+                    mode = Some(if tokens[0] == "WALK" {
+                        Mode::Walk
+                    } else {
+                        Mode::Run
+                    });
+                    continue;
+                }
+                "AND" => Gate::And,
+                "OR" => Gate::Or,
+                "NOT" => Gate::Not,
+                _ => {
+                    return Err(script_error(
+                        line_number,
+                        format!("unknown instruction {:?}", line.trim()),
+                    ))
+                }
+            };
+            if tokens.len() != 3 {
+                return Err(script_error(
+                    line_number,
+                    format!("expected two registers in {:?}", line.trim()),
+                ));
+            }
+            let register = |name: &str| {
+                Register::parse(name)
+                    .ok_or_else(|| script_error(line_number, format!("unknown register {}", name)))
+            };
+            let (x, y) = (register(tokens[1])?, register(tokens[2])?);
+            if let Register::Sensor(_) = y {
+                return Err(script_error(
+                    line_number,
+                    format!("register {} is read-only", y),
+                ));
+            }
+            instructions.push(SpringInstruction { gate, x, y });
+        }
+
+        let mode: Mode = mode
+            .ok_or_else(|| script_error(text.lines().count(), "missing WALK or RUN".to_string()))?;
+        let script = Springscript { instructions, mode };
+        script.validate()?;
+        Ok(script)
+    }
+
+    // Check the limits the droid enforces: the instruction count, and only
+    // the sensors the mode has.
+    pub fn validate(&self) -> Result<(), IntcodeError> {
+        if self.instructions.len() > MAX_INSTRUCTIONS {
+            return Err(script_error(
+                MAX_INSTRUCTIONS + 1,
+                format!("more than {} instructions", MAX_INSTRUCTIONS),
+            ));
+        }
+        for (index, instruction) in self.instructions.iter().enumerate() {
+            if let Register::Sensor(sensor) = instruction.x {
+                if sensor >= self.mode.sensors() {
+                    return Err(script_error(
+                        index + 1,
+                        format!(
+                            "sensor {} is not available with {}",
+                            instruction.x,
+                            self.mode.command()
+                        ),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Lines to send to the droid, the mode command last.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .instructions
+            .iter()
+            .map(|instruction| instruction.to_string())
+            .collect();
+        lines.push(self.mode.command().to_string());
+        lines
+    }
+
+    // Whether the droid jumps, given what its sensors read.
+    pub fn jumps(&self, ground: &[bool]) -> bool {
+        let (mut t, mut j): (bool, bool) = (false, false);
+        for instruction in &self.instructions {
+            let x: bool = match instruction.x {
+                Register::Sensor(sensor) => ground[sensor],
+                Register::T => t,
+                Register::J => j,
+            };
+            let y: &mut bool = if instruction.y == Register::T {
+                &mut t
+            } else {
+                &mut j
+            };
+            *y = match instruction.gate {
+                Gate::And => x && *y,
+                Gate::Or => x || *y,
+                Gate::Not => !x,
+            };
+        }
+        j
+    }
+
+    // Walk the droid across `hull`, true for ground, starting on its first
+    // tile. Err holds the tile it falls through.
+    pub fn simulate(&self, hull: &[bool]) -> Result<(), usize> {
+        cross(hull, self.mode.sensors(), |ground| self.jumps(ground))
+    }
+}
+
+impl fmt::Display for Springscript {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.lines().join("\n"))
+    }
+}
+
+// Past the end of a known hull there is only ground.
+fn sensor_readings(hull: &[bool], position: usize, sensors: usize) -> Vec<bool> {
+    (1..=sensors)
+        .map(|distance| *hull.get(position + distance).unwrap_or(&true))
+        .collect()
+}
+
+fn cross(
+    hull: &[bool],
+    sensors: usize,
+    mut jumps: impl FnMut(&[bool]) -> bool,
+) -> Result<(), usize> {
+    let mut position: usize = 0;
+    while position < hull.len() {
+        if !hull[position] {
+            return Err(position);
+        }
+        let ground: Vec<bool> = sensor_readings(hull, position, sensors);
+        position += if jumps(&ground) { 4 } else { 1 };
+    }
+    Ok(())
+}
+
+// Hull from the droid's report after falling, starting on the tile it
+// stood on in the first frame.
+pub fn failed_hull(text: &str) -> Option<Vec<bool>> {
+    let lines: Vec<&str> = text
+        .lines()
+        .skip_while(|line| !line.starts_with("Didn't make it across"))
+        .collect();
+    let droid: usize = lines.iter().position(|line| line.contains('@'))?;
+    let column: usize = lines[droid].find('@')?;
+    let hull: &str = lines[droid..]
+        .iter()
+        .take_while(|line| !line.is_empty())
+        .last()?;
+    Some(hull.chars().skip(column).map(|c| c == '#').collect())
+}
+
+// Truth table over the sensor readings being considered, one bit each.
+type Table = Vec<u64>;
+
+// Ways to change J in one step, with the instructions each takes: combine it
+// with a sensor, or with a negated sensor by way of T, overwrite it with a
+// negated sensor, or negate it.
+fn steps(sensors: usize) -> Vec<Vec<SpringInstruction>> {
+    let instruction = |gate: Gate, x: Register, y: Register| SpringInstruction { gate, x, y };
+    let mut steps: Vec<Vec<SpringInstruction>> = Vec::new();
+    for sensor in (0..sensors).map(Register::Sensor) {
+        for gate in [Gate::And, Gate::Or] {
+            steps.push(vec![instruction(gate, sensor, Register::J)]);
+        }
+        steps.push(vec![instruction(Gate::Not, sensor, Register::J)]);
+        for gate in [Gate::And, Gate::Or] {
+            steps.push(vec![
+                instruction(Gate::Not, sensor, Register::T),
+                instruction(gate, Register::T, Register::J),
+            ]);
+        }
+    }
+    steps.push(vec![instruction(Gate::Not, Register::J, Register::J)]);
+    steps
+}
+
+// Script that gets the droid across every hull in `hulls`. This is a
+// heuristic rather than a search over all springscript: it only tries
+// scripts that build J up one sensor at a time and only use T to negate a
+// sensor, which is how the usual hand-written ones look, and returns one of
+// those with the fewest instructions. The search goes by instruction count
+// over what J holds, so scripts computing the same jump decisions are only
+// explored once. None means no script of that shape works, not that no
+// script does.
+pub fn synthesize(mode: Mode, hulls: &[Vec<bool>]) -> Option<Springscript> {
+    let sensors: usize = mode.sensors();
+    // every reading the droid can get standing on ground somewhere, and
+    // which one it gets on each tile of each hull
+    let mut readings: Vec<Vec<bool>> = Vec::new();
+    let mut index: HashMap<Vec<bool>, usize> = HashMap::new();
+    let mut hull_readings: Vec<Vec<usize>> = Vec::new();
+    for hull in hulls {
+        let mut tiles: Vec<usize> = vec![usize::MAX; hull.len()];
+        for position in (0..hull.len()).filter(|position| hull[*position]) {
+            let reading: Vec<bool> = sensor_readings(hull, position, sensors);
+            tiles[position] = *index.entry(reading.clone()).or_insert_with(|| {
+                readings.push(reading);
+                readings.len() - 1
+            });
+        }
+        hull_readings.push(tiles);
+    }
+    let words: usize = readings.len().div_ceil(64).max(1);
+    let bit = |table: &Table, reading: usize| table[reading / 64] >> (reading % 64) & 1 == 1;
+    let mut all: Table = vec![0; words];
+    for reading in 0..readings.len() {
+        all[reading / 64] |= 1 << (reading % 64);
+    }
+    let table = |sensor: usize| -> Table {
+        let mut table: Table = vec![0; words];
+        for (reading, ground) in readings.iter().enumerate() {
+            if ground[sensor] {
+                table[reading / 64] |= 1 << (reading % 64);
+            }
+        }
+        table
+    };
+    let sensor_tables: Vec<Table> = (0..sensors).map(table).collect();
+    let apply = |instruction: &SpringInstruction, t: &mut [u64], j: &mut [u64]| {
+        for word in 0..words {
+            let x: u64 = match instruction.x {
+                Register::Sensor(sensor) => sensor_tables[sensor][word],
+                Register::T => t[word],
+                Register::J => j[word],
+            };
+            let y: &mut u64 = if instruction.y == Register::T {
+                &mut t[word]
+            } else {
+                &mut j[word]
+            };
+            *y = match instruction.gate {
+                Gate::And => x & *y,
+                Gate::Or => x | *y,
+                Gate::Not => !x & all[word],
+            };
+        }
+    };
+    // same as `cross`, with the readings looked up
+    let solves = |jump: &Table| -> bool {
+        hull_readings.iter().all(|tiles| {
+            let mut position: usize = 0;
+            while position < tiles.len() {
+                match tiles[position] {
+                    usize::MAX => return false,
+                    reading if bit(jump, reading) => position += 4,
+                    _ => position += 1,
+                }
+            }
+            true
+        })
+    };
+
+    let steps: Vec<Vec<SpringInstruction>> = steps(sensors);
+    // J tables, and the step and table each was first reached from
+    let mut tables: Vec<Table> = vec![vec![0; words]];
+    let mut parents: Vec<Option<(usize, usize)>> = vec![None];
+    let mut seen: HashSet<Table> = HashSet::from([tables[0].clone()]);
+    // tables by the number of instructions needed to reach them
+    let mut by_length: Vec<Vec<usize>> = vec![Vec::new(); MAX_INSTRUCTIONS + 1];
+    by_length[0].push(0);
+
+    // tables are checked as they are reached, so the search can stop as
+    // soon as no shorter script is left to find
+    let (mut t, mut j): (Table, Table) = (vec![0; words], vec![0; words]);
+    let mut found: Option<(usize, usize)> = solves(&tables[0]).then_some((0, 0));
+    for length in 0..MAX_INSTRUCTIONS {
+        if found.is_some_and(|(shortest, _)| shortest <= length + 1) {
+            break;
+        }
+        let mut position: usize = 0;
+        while position < by_length[length].len() {
+            let state: usize = by_length[length][position];
+            position += 1;
+            for (step, instructions) in steps.iter().enumerate() {
+                let reached: usize = length + instructions.len();
+                if reached > MAX_INSTRUCTIONS {
+                    continue;
+                }
+                t.fill(0);
+                j.copy_from_slice(&tables[state]);
+                for instruction in instructions {
+                    apply(instruction, &mut t, &mut j);
+                }
+                if !seen.contains(&j) {
+                    seen.insert(j.clone());
+                    tables.push(j.clone());
+                    parents.push(Some((state, step)));
+                    by_length[reached].push(tables.len() - 1);
+                    if found.is_none_or(|(shortest, _)| reached < shortest) && solves(&j) {
+                        found = Some((reached, tables.len() - 1));
+                    }
+                }
+            }
+        }
+    }
+
+    let mut instructions: Vec<SpringInstruction> = Vec::new();
+    let (_, mut state): (usize, usize) = found?;
+    while let Some((parent, step)) = parents[state] {
+        instructions.extend(steps[step].iter().rev());
+        state = parent;
+    }
+    instructions.reverse();
+    Some(Springscript { instructions, mode })
+}
+
+// Hull damage the droid reports once a script gets it across. Scripts are
+// synthesised for the hulls seen so far, learning a new hull from every
+// fall.
+pub fn solve(program: &Memory, mode: Mode) -> Result<i64, IntcodeError> {
+    let mut hulls: Vec<Vec<bool>> = Vec::new();
+    loop {
+        let script: Springscript =
+            synthesize(mode, &hulls).ok_or_else(|| IntcodeError::NoSolution {
+                message: format!("no script found for the {} hulls seen", hulls.len()),
+            })?;
+        let mut computer = AsciiComputer::new(program.clone());
+        computer.read_until_prompt()?;
+        for line in script.lines() {
+            computer.send_line(&line)?;
+        }
+        let text: String = computer.read_until_prompt()?;
+
+        if let Some(damage) = computer.answer() {
+            return Ok(damage);
+        }
+        match failed_hull(&text) {
+            // the script already gets across this one here, so the droid
+            // and the simulation disagree and the next script would too
+            Some(hull) if hulls.contains(&hull) => {
+                return Err(IntcodeError::NoSolution {
+                    message: format!("the droid fell again on a hull it was taught:\n{}", text),
+                })
+            }
+            Some(hull) => hulls.push(hull),
+            None => {
+                return Err(IntcodeError::NoSolution {
+                    message: format!("the droid neither got across nor fell:\n{}", text),
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hull(text: &str) -> Vec<bool> {
+        text.chars().map(|c| c == '#').collect()
+    }
+
+    #[test]
+    fn parses_validates_and_prints() {
+        let text: &str = "NOT A J\nNOT B T\nOR T J\nNOT C T\nOR T J\nAND D J\nWALK\n";
+        let script: Springscript = Springscript::parse(text).unwrap();
+        assert_eq!(script.instructions.len(), 6);
+        assert_eq!(script.mode, Mode::Walk);
+        assert_eq!(script.to_string() + "\n", text);
+
+        let error = |text: &str| Springscript::parse(text).unwrap_err().to_string();
+        assert_eq!(
+            error("NOT A J\nAND E J\nWALK"),
+            "springscript line 2: sensor E is not available with WALK"
+        );
+        assert_eq!(
+            error("OR A B\nRUN"),
+            "springscript line 1: register B is read-only"
+        );
+        assert_eq!(
+            error(&"NOT A J\n".repeat(16)),
+            "springscript line 16: missing WALK or RUN"
+        );
+        assert_eq!(
+            error(&("NOT A J\n".repeat(16) + "RUN")),
+            "springscript line 16: more than 15 instructions"
+        );
+        assert_eq!(
+            error("JUMP A J\nWALK"),
+            "springscript line 1: unknown instruction \"JUMP A J\""
+        );
+    }
+
+    #[test]
+    fn simulates_the_puzzle_example() {
+        let report: &str = "Walking...\n\nDidn't make it across:\n\n.................\n.................\n@................\n#####.###########\n\n.................\n.................\n.@...............\n#####.###########\n\n";
+        let hull: Vec<bool> = failed_hull(report).unwrap();
+        assert_eq!(hull, super::tests::hull("#####.###########"));
+
+        // jumping into any hole it sees four tiles ahead
+        let script: Springscript = Springscript::parse("NOT D J\nWALK").unwrap();
+        assert_eq!(script.simulate(&hull), Err(5));
+        let script: Springscript = Springscript::parse("NOT A J\nWALK").unwrap();
+        assert_eq!(script.simulate(&hull), Ok(()));
+    }
+
+    #[test]
+    fn synthesizes_short_scripts() {
+        assert_eq!(synthesize(Mode::Walk, &[]).unwrap().instructions.len(), 0);
+        let hulls: Vec<Vec<bool>> = vec![hull("#####.###########")];
+        let script: Springscript = synthesize(Mode::Walk, &hulls).unwrap();
+        assert_eq!(script.instructions.len(), 1);
+        assert!(script.simulate(&hulls[0]).is_ok());
+
+        // needs to wait for the last moment on the first, and jump early on
+        // the second
+        let hulls: Vec<Vec<bool>> = vec![hull("#####...#########"), hull("#####..#.########")];
+        let script: Springscript = synthesize(Mode::Walk, &hulls).unwrap();
+        assert!(hulls.iter().all(|hull| script.simulate(hull).is_ok()));
+        assert!(script.instructions.len() >= 2);
+    }
+
+    #[test]
+    fn stops_when_the_droid_keeps_falling_on_the_same_hull() {
+        // falls into the same hole whatever script it is given
+        let values: Vec<i64> = crate::assemble(
+            r#"
+            start:  in [c]
+                    eq [c], #75, [k]
+                    jz [k], #start
+                    rbo #text
+            print:  jz [rb+0], #done
+                    out [rb+0]
+                    rbo #1
+                    jnz #1, #print
+            done:   hlt
+            c:      .data 0
+            k:      .data 0
+            text:   .string "Didn't make it across:\n\n.................\n@................\n#####.###########\n\n"
+                    .data 0
+            "#,
+        )
+        .unwrap();
+        match solve(&Memory::new(values), Mode::Walk) {
+            Err(IntcodeError::NoSolution { message }) => {
+                assert!(message.starts_with("the droid fell again"))
+            }
+            other => panic!("expected NoSolution, got {:?}", other),
+        }
+    }
+}
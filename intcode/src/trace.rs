@@ -0,0 +1,322 @@
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::ops::Range;
+
+use crate::computer::{parse_instruction, IntcodeComputer, Operation, OperationType, StepResult};
+use crate::disassembler::encode;
+use crate::error::IntcodeError;
+
+// Binary traces start with this header, followed by one record per event:
+// pointer, instruction word and relative base as little-endian i64, a flags
+// byte (1 = write, 2 = input, 4 = output), the operand count as a byte, the
+// operands, then write address and value, input and output when flagged.
+const BINARY_MAGIC: &[u8; 4] = b"ICTR";
+const BINARY_VERSION: u8 = 1;
+
+// One executed instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEvent {
+    pub pointer: i64,
+    pub operation: Operation,
+    // relative base before the instruction ran
+    pub relative_base: i64,
+    // values of the parameters the instruction reads
+    pub operands: Vec<i64>,
+    // written address and the value written there
+    pub write: Option<(i64, i64)>,
+    pub input: Option<i64>,
+    pub output: Option<i64>,
+}
+
+fn json_option(value: Option<i64>) -> String {
+    value.map_or("null".to_string(), |value| value.to_string())
+}
+
+impl TraceEvent {
+    pub fn to_json(&self) -> String {
+        let modes: Vec<String> = (1..=self.operation.operation.parameters())
+            .map(|parameter| (self.operation.parameter_mode(parameter) as i64).to_string())
+            .collect();
+        let operands: Vec<String> = self
+            .operands
+            .iter()
+            .map(|value| value.to_string())
+            .collect();
+        format!(
+            "{{\"pointer\":{},\"op\":\"{}\",\"modes\":[{}],\"relative_base\":{},\"operands\":[{}],\"write_address\":{},\"write_value\":{},\"input\":{},\"output\":{}}}",
+            self.pointer,
+            self.operation.operation.mnemonic(),
+            modes.join(","),
+            self.relative_base,
+            operands.join(","),
+            json_option(self.write.map(|(address, _)| address)),
+            json_option(self.write.map(|(_, value)| value)),
+            json_option(self.input),
+            json_option(self.output),
+        )
+    }
+
+    fn to_binary(&self) -> Vec<u8> {
+        let flags: u8 = self.write.is_some() as u8
+            | (self.input.is_some() as u8) << 1
+            | (self.output.is_some() as u8) << 2;
+
+        let mut record: Vec<u8> = Vec::new();
+        for value in [self.pointer, encode(&self.operation), self.relative_base] {
+            record.extend(value.to_le_bytes());
+        }
+        record.push(flags);
+        record.push(self.operands.len() as u8);
+        let optional = self
+            .write
+            .into_iter()
+            .flat_map(|(address, value)| [address, value])
+            .chain(self.input)
+            .chain(self.output);
+        for value in self.operands.iter().copied().chain(optional) {
+            record.extend(value.to_le_bytes());
+        }
+        record
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    JsonLines,
+    Binary,
+}
+
+// Which instructions end up in the trace. Empty filters let everything pass.
+#[derive(Debug, Clone, Default)]
+pub struct TraceFilter {
+    pub addresses: Option<Range<i64>>,
+    pub operations: Vec<OperationType>,
+}
+
+impl TraceFilter {
+    pub fn matches(&self, pointer: i64, operation: OperationType) -> bool {
+        self.addresses
+            .as_ref()
+            .is_none_or(|addresses| addresses.contains(&pointer))
+            && (self.operations.is_empty() || self.operations.contains(&operation))
+    }
+}
+
+fn trace_error(error: std::io::Error) -> IntcodeError {
+    IntcodeError::Trace {
+        message: error.to_string(),
+    }
+}
+
+// Runs a computer while writing every executed instruction to `writer`.
+pub struct Tracer<W: Write> {
+    writer: W,
+    format: TraceFormat,
+    filter: TraceFilter,
+    started: bool,
+}
+
+impl<W: Write> Tracer<W> {
+    pub fn new(writer: W, format: TraceFormat) -> Self {
+        Tracer::with_filter(writer, format, TraceFilter::default())
+    }
+
+    pub fn with_filter(writer: W, format: TraceFormat, filter: TraceFilter) -> Self {
+        Tracer {
+            writer,
+            format,
+            filter,
+            started: false,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn record(&mut self, event: &TraceEvent) -> Result<(), IntcodeError> {
+        match self.format {
+            TraceFormat::JsonLines => writeln!(self.writer, "{}", event.to_json()),
+            TraceFormat::Binary => {
+                let mut record: Vec<u8> = Vec::new();
+                if !self.started {
+                    record.extend(BINARY_MAGIC);
+                    record.push(BINARY_VERSION);
+                    self.started = true;
+                }
+                record.extend(event.to_binary());
+                self.writer.write_all(&record)
+            }
+        }
+        .map_err(trace_error)
+    }
+
+    // `IntcodeComputer::step` that records the instruction it executed.
+    pub fn step(
+        &mut self,
+        computer: &mut IntcodeComputer,
+        input: &mut VecDeque<i64>,
+    ) -> Result<StepResult, IntcodeError> {
+        if computer.halted {
+            return Ok(StepResult::Halted);
+        }
+        let pointer: i64 = computer.pointer;
+        let operation: Operation = computer.parse_instruction()?;
+        if !self.filter.matches(pointer, operation.operation) {
+            return computer.step(input);
+        }
+
+        let relative_base: i64 = computer.relative_base;
+        let operands: Vec<i64> = computer.read_operands()?;
+        let write_address: Option<i64> = computer.write_address()?;
+        let next_input: Option<i64> = input.front().copied();
+
+        let result: StepResult = computer.step(input)?;
+        if result == StepResult::NeedsInput {
+            return Ok(result);
+        }
+        self.record(&TraceEvent {
+            pointer,
+            operation,
+            relative_base,
+            operands,
+            write: write_address.map(|address| (address, computer.program.read(address as usize))),
+            input: next_input.filter(|_| operation.operation == OperationType::CPY),
+            output: match result {
+                StepResult::Output(value) => Some(value),
+                _ => None,
+            },
+        })?;
+        Ok(result)
+    }
+
+    // Traced `IntcodeComputer::run`: runs until input is missing or halt.
+    pub fn run(
+        &mut self,
+        computer: &mut IntcodeComputer,
+        input: &mut VecDeque<i64>,
+    ) -> Result<Vec<i64>, IntcodeError> {
+        let mut output: Vec<i64> = Vec::new();
+        loop {
+            match self.step(computer, input)? {
+                StepResult::Output(value) => output.push(value),
+                StepResult::Executed => {}
+                StepResult::NeedsInput | StepResult::Halted => break,
+            }
+        }
+        self.writer.flush().map_err(trace_error)?;
+        Ok(output)
+    }
+}
+
+fn read_i64<R: Read>(reader: &mut R) -> Result<i64, IntcodeError> {
+    let mut bytes: [u8; 8] = [0; 8];
+    reader.read_exact(&mut bytes).map_err(trace_error)?;
+    Ok(i64::from_le_bytes(bytes))
+}
+
+// Decode a binary trace back into events.
+pub fn read_binary_trace<R: Read>(mut reader: R) -> Result<Vec<TraceEvent>, IntcodeError> {
+    let mut data: Vec<u8> = Vec::new();
+    reader.read_to_end(&mut data).map_err(trace_error)?;
+    if data.is_empty() {
+        return Ok(Vec::new());
+    }
+    if data.len() < 5 || &data[..4] != BINARY_MAGIC || data[4] != BINARY_VERSION {
+        return Err(IntcodeError::Trace {
+            message: "not a version 1 binary trace".to_string(),
+        });
+    }
+
+    let mut reader: &[u8] = &data[5..];
+    let mut events: Vec<TraceEvent> = Vec::new();
+    while !reader.is_empty() {
+        let pointer: i64 = read_i64(&mut reader)?;
+        let operation: Operation = parse_instruction(pointer, read_i64(&mut reader)?)?;
+        let relative_base: i64 = read_i64(&mut reader)?;
+        let mut header: [u8; 2] = [0; 2];
+        reader.read_exact(&mut header).map_err(trace_error)?;
+        let [flags, count] = header;
+
+        let operands: Vec<i64> = (0..count)
+            .map(|_| read_i64(&mut reader))
+            .collect::<Result<_, _>>()?;
+        let write: Option<(i64, i64)> = if flags & 1 != 0 {
+            Some((read_i64(&mut reader)?, read_i64(&mut reader)?))
+        } else {
+            None
+        };
+        let input: Option<i64> = if flags & 2 != 0 {
+            Some(read_i64(&mut reader)?)
+        } else {
+            None
+        };
+        let output: Option<i64> = if flags & 4 != 0 {
+            Some(read_i64(&mut reader)?)
+        } else {
+            None
+        };
+        events.push(TraceEvent {
+            pointer,
+            operation,
+            relative_base,
+            operands,
+            write,
+            input,
+            output,
+        });
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assemble, Memory};
+
+    fn computer() -> IntcodeComputer {
+        let values: Vec<i64> = assemble("in [rb+9]\nmul [rb+9], #3, [10]\nout [10]\nhlt").unwrap();
+        IntcodeComputer::new(Memory::new(values))
+    }
+
+    #[test]
+    fn json_lines_describe_each_instruction() {
+        let mut tracer = Tracer::new(Vec::new(), TraceFormat::JsonLines);
+        let output: Vec<i64> = tracer
+            .run(&mut computer(), &mut VecDeque::from([7]))
+            .unwrap();
+        assert_eq!(output, vec![21]);
+
+        let trace: String = String::from_utf8(tracer.into_inner()).unwrap();
+        let lines: Vec<&str> = trace.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[0],
+            "{\"pointer\":0,\"op\":\"in\",\"modes\":[2],\"relative_base\":0,\"operands\":[],\"write_address\":9,\"write_value\":7,\"input\":7,\"output\":null}"
+        );
+        assert_eq!(
+            lines[1],
+            "{\"pointer\":2,\"op\":\"mul\",\"modes\":[2,1,0],\"relative_base\":0,\"operands\":[7,3],\"write_address\":10,\"write_value\":21,\"input\":null,\"output\":null}"
+        );
+    }
+
+    #[test]
+    fn binary_trace_reads_back_and_honours_filters() {
+        let filter = TraceFilter {
+            addresses: Some(2..100),
+            operations: vec![OperationType::MUL, OperationType::OUT],
+        };
+        let mut tracer = Tracer::with_filter(Vec::new(), TraceFormat::Binary, filter);
+        tracer
+            .run(&mut computer(), &mut VecDeque::from([7]))
+            .unwrap();
+
+        let events: Vec<TraceEvent> = read_binary_trace(&tracer.into_inner()[..]).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].operands, vec![7, 3]);
+        assert_eq!(events[0].write, Some((10, 21)));
+        assert_eq!(events[1].pointer, 6);
+        assert_eq!(events[1].output, Some(21));
+        assert!(read_binary_trace(&b"junk"[..]).is_err());
+    }
+}
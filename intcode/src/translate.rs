@@ -0,0 +1,351 @@
+use std::collections::VecDeque;
+
+use crate::computer::{IntcodeComputer, OperationType, ParameterMode, StepResult};
+use crate::disassembler::{decode, encode, find_code, Instruction};
+use crate::error::IntcodeError;
+
+// Signature of the functions `translate` generates. They run a computer
+// until it outputs, needs input or halts, like a loop over
+// `IntcodeComputer::step`. `StepResult::Executed` means they stopped without
+// any of those: the program rewrote its own code or jumped somewhere that
+// was not translated, and the interpreter has to carry on from there.
+pub type TranslatedFn =
+    fn(&mut IntcodeComputer, &mut VecDeque<i64>) -> Result<StepResult, IntcodeError>;
+
+// Runs a computer through translated code, and through the interpreter once
+// the translated code can no longer be trusted for it.
+#[derive(Debug, Clone, Copy)]
+pub struct Translated {
+    function: TranslatedFn,
+    interpreting: bool,
+}
+
+impl Translated {
+    // `image` is the program `function` was translated from. A computer
+    // loaded with anything else is interpreted from the start.
+    pub fn new(function: TranslatedFn, image: &[i64], computer: &IntcodeComputer) -> Self {
+        let matches: bool = image
+            .iter()
+            .enumerate()
+            .all(|(address, value)| computer.program.read(address) == *value);
+        Translated {
+            function,
+            interpreting: !matches,
+        }
+    }
+
+    pub fn is_interpreting(&self) -> bool {
+        self.interpreting
+    }
+
+    // Run until the next output, missing input or halt.
+    pub fn resume(
+        &mut self,
+        computer: &mut IntcodeComputer,
+        input: &mut VecDeque<i64>,
+    ) -> Result<StepResult, IntcodeError> {
+        if !self.interpreting {
+            match (self.function)(computer, input)? {
+                StepResult::Executed => self.interpreting = true,
+                result => return Ok(result),
+            }
+        }
+        loop {
+            match computer.step(input)? {
+                StepResult::Executed => continue,
+                result => return Ok(result),
+            }
+        }
+    }
+
+    // Same as `IntcodeComputer::run_until_output`.
+    pub fn run_until_output(
+        &mut self,
+        computer: &mut IntcodeComputer,
+        input: &mut VecDeque<i64>,
+    ) -> Result<Option<i64>, IntcodeError> {
+        match self.resume(computer, input)? {
+            StepResult::Output(value) => Ok(Some(value)),
+            _ => Ok(None),
+        }
+    }
+
+    // Same as `IntcodeComputer::run`.
+    pub fn run(
+        &mut self,
+        computer: &mut IntcodeComputer,
+        input: &mut VecDeque<i64>,
+    ) -> Result<Vec<i64>, IntcodeError> {
+        let mut output: Vec<i64> = Vec::new();
+        while let StepResult::Output(value) = self.resume(computer, input)? {
+            output.push(value);
+        }
+        Ok(output)
+    }
+}
+
+// Rust expression for the value of a read parameter.
+fn operand(instruction: &Instruction, parameter: usize) -> String {
+    let value: i64 = instruction.parameters[parameter - 1];
+    match instruction.operation.parameter_mode(parameter) {
+        ParameterMode::ImmediateMode => format!("{}i64", value),
+        ParameterMode::PositionMode => format!("read(memory, {}, {})?", instruction.address, value),
+        ParameterMode::RelativeMode => format!(
+            "read(memory, {}, {})?",
+            instruction.address,
+            relative(value)
+        ),
+    }
+}
+
+fn relative(offset: i64) -> String {
+    match offset {
+        0 => "rb".to_string(),
+        offset => format!("rb.wrapping_add({})", offset),
+    }
+}
+
+// Statements that store `value` through parameter `parameter` and move on.
+fn store(instruction: &Instruction, parameter: usize, value: &str, code: &[bool]) -> String {
+    let address: usize = instruction.address;
+    let target: i64 = instruction.parameters[parameter - 1];
+    let next: usize = address + instruction.size();
+    match instruction.operation.parameter_mode(parameter) {
+        ParameterMode::ImmediateMode => format!(
+            "return Err(IntcodeError::ImmediateModeWrite {{ pointer: {}, instruction: {}, parameter: {} }});",
+            address,
+            encode(&instruction.operation),
+            parameter
+        ),
+        ParameterMode::PositionMode => {
+            let rewrites_code: bool = target >= 0 && code.get(target as usize) == Some(&true);
+            let mut text: String = format!(
+                "write(memory, {}, {}, {})?; pc = {};",
+                address, target, value, next
+            );
+            if rewrites_code {
+                text.push_str(" return Ok(StepResult::Executed);");
+            }
+            text
+        }
+        ParameterMode::RelativeMode => format!(
+            "let address: i64 = {}; write(memory, {}, address, {})?; pc = {}; if is_code(address) {{ return Ok(StepResult::Executed); }}",
+            relative(target),
+            address,
+            value,
+            next
+        ),
+    }
+}
+
+fn arm(instruction: &Instruction, code: &[bool]) -> String {
+    let next: usize = instruction.address + instruction.size();
+    match instruction.operation.operation {
+        OperationType::SUM | OperationType::MUL | OperationType::LTH | OperationType::EQL => {
+            let value: &str = match instruction.operation.operation {
+                OperationType::SUM => "a.wrapping_add(b)",
+                OperationType::MUL => "a.wrapping_mul(b)",
+                OperationType::LTH => "(a < b) as i64",
+                _ => "(a == b) as i64",
+            };
+            format!(
+                "let a: i64 = {}; let b: i64 = {}; {}",
+                operand(instruction, 1),
+                operand(instruction, 2),
+                store(instruction, 3, value, code)
+            )
+        }
+        OperationType::CPY => format!(
+            "let value: i64 = match input.pop_front() {{ Some(value) => value, None => return Ok(StepResult::NeedsInput) }}; {}",
+            store(instruction, 1, "value", code)
+        ),
+        OperationType::OUT => format!(
+            "let value: i64 = {}; pc = {}; return Ok(StepResult::Output(value));",
+            operand(instruction, 1),
+            next
+        ),
+        OperationType::JIT | OperationType::JIF => format!(
+            "let a: i64 = {}; let b: i64 = {}; pc = if a {} 0 {{ b }} else {{ {} }};",
+            operand(instruction, 1),
+            operand(instruction, 2),
+            if instruction.operation.operation == OperationType::JIT {
+                "!="
+            } else {
+                "=="
+            },
+            next
+        ),
+        OperationType::ARB => format!(
+            "rb = rb.wrapping_add({}); pc = {};",
+            operand(instruction, 1),
+            next
+        ),
+        OperationType::END => "return Ok(StepResult::Halted);".to_string(),
+    }
+}
+
+// Rust source for a function `name` that runs the Intcode image `values`,
+// with one match arm per instruction reachable from address 0, and a
+// `<NAME>_IMAGE` constant holding the image itself. The source only uses
+// the public API of this crate; pass the function and the image to
+// `Translated::new` to run it.
+pub fn translate(values: &[i64], name: &str) -> String {
+    let (starts, _) = find_code(values);
+    let instructions: Vec<Instruction> = (0..values.len())
+        .filter(|address| starts[*address])
+        .filter_map(|address| decode(values, address))
+        .collect();
+    let mut code: Vec<bool> = vec![false; values.len()];
+    for instruction in &instructions {
+        code[instruction.address..instruction.address + instruction.size()].fill(true);
+    }
+
+    // cells of translated instructions, as inclusive ranges
+    let mut ranges: Vec<String> = Vec::new();
+    let mut address: usize = 0;
+    while address < code.len() {
+        if !code[address] {
+            address += 1;
+            continue;
+        }
+        let end: usize = (address..code.len())
+            .find(|end| !code[*end])
+            .unwrap_or(code.len());
+        ranges.push(format!("{}..={}", address, end - 1));
+        address = end;
+    }
+    if ranges.is_empty() {
+        ranges.push("-1".to_string());
+    }
+
+    let image: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+    let mut source: String = String::new();
+    source.push_str(&format!(
+        "// Translated from a {} value Intcode image by intcode::translate.\n\n",
+        values.len()
+    ));
+    source.push_str(&format!(
+        "pub const {}_IMAGE: &[i64] = &[{}];\n\n",
+        name.to_uppercase(),
+        image.join(", ")
+    ));
+    source.push_str(&format!(
+        "#[allow(unused_mut, unused_variables, dead_code, clippy::all)]
+pub fn {}(
+    computer: &mut intcode::IntcodeComputer,
+    input: &mut std::collections::VecDeque<i64>,
+) -> Result<intcode::StepResult, intcode::IntcodeError> {{
+    use intcode::{{IntcodeError, Memory, StepResult}};
+
+    fn read(memory: &Memory, pointer: i64, address: i64) -> Result<i64, IntcodeError> {{
+        if address < 0 {{
+            return Err(IntcodeError::NegativeAddress {{ pointer, address }});
+        }}
+        Ok(memory.read(address as usize))
+    }}
+
+    fn write(memory: &mut Memory, pointer: i64, address: i64, value: i64) -> Result<(), IntcodeError> {{
+        if address < 0 {{
+            return Err(IntcodeError::NegativeAddress {{ pointer, address }});
+        }}
+        memory.write(address as usize, value);
+        Ok(())
+    }}
+
+    fn is_code(address: i64) -> bool {{
+        matches!(address, {})
+    }}
+
+    if computer.halted {{
+        return Ok(StepResult::Halted);
+    }}
+    let memory: &mut Memory = &mut computer.program;
+    let mut pc: i64 = computer.pointer;
+    let mut rb: i64 = computer.relative_base;
+    let mut execute = || -> Result<StepResult, IntcodeError> {{
+        loop {{
+            match pc {{
+",
+        name,
+        ranges.join(" | ")
+    ));
+    for instruction in &instructions {
+        source.push_str(&format!(
+            "                // {}\n                {} => {{ {} }}\n",
+            instruction,
+            instruction.address,
+            arm(instruction, &code)
+        ));
+    }
+    source.push_str(
+        "                _ => return Ok(StepResult::Executed),
+            }
+        }
+    };
+    let result: Result<StepResult, IntcodeError> = execute();
+    computer.pointer = pc;
+    computer.relative_base = rb;
+    if result == Ok(StepResult::Halted) {
+        computer.halted = true;
+    }
+    result
+}
+",
+    );
+    source
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assemble, Memory};
+
+    #[test]
+    fn emits_one_arm_per_instruction() {
+        let values: Vec<i64> = assemble(
+            "
+                    in [rb+5]
+                    add #7, #0, [patch+1]
+            patch:  out [0]
+                    jnz #1, #0
+            ",
+        )
+        .unwrap();
+        let source: String = translate(&values, "echo");
+        assert!(source.contains("pub const ECHO_IMAGE: &[i64] = &[203, 5, 1101"));
+        assert!(source.contains("pub fn echo("));
+        assert!(source.contains("let address: i64 = rb.wrapping_add(5);"));
+        assert!(source.contains("matches!(address, 0..=10)"));
+        assert!(source.contains(
+            "2 => { let a: i64 = 7i64; let b: i64 = 0i64; write(memory, 2, 7, a.wrapping_add(b))?; pc = 6; return Ok(StepResult::Executed); }"
+        ));
+        assert!(source.contains("6 => { let value: i64 = read(memory, 6, 0)?; pc = 8;"));
+        assert!(source.contains("pc = if a != 0 { b } else { 11 };"));
+        assert_eq!(source.matches(" => { ").count(), 4);
+    }
+
+    // stands in for translated code that gives up straight away
+    fn gives_up(
+        _computer: &mut IntcodeComputer,
+        _input: &mut VecDeque<i64>,
+    ) -> Result<StepResult, IntcodeError> {
+        Ok(StepResult::Executed)
+    }
+
+    #[test]
+    fn falls_back_to_the_interpreter() {
+        let values: Vec<i64> = assemble("in [9]\nmul [9], #3, [9]\nout [9]\nhlt").unwrap();
+        let mut computer = IntcodeComputer::new(Memory::new(values.clone()));
+        let mut translated = Translated::new(gives_up, &values, &computer);
+        assert!(!translated.is_interpreting());
+        let output: Vec<i64> = translated
+            .run(&mut computer, &mut VecDeque::from([5]))
+            .unwrap();
+        assert_eq!(output, vec![15]);
+        assert!(translated.is_interpreting());
+        assert!(computer.halted);
+
+        let other = IntcodeComputer::new(Memory::new(vec![99]));
+        assert!(Translated::new(gives_up, &values, &other).is_interpreting());
+    }
+}